@@ -0,0 +1,107 @@
+//! Pack the user programs into an easy-fs image
+//!
+//! Every program in the source directory (`user/src/bin`) is looked up by
+//! name in the target directory (the built ELF files) and written to the
+//! root directory of a fresh image `fs.img` in the target directory.
+use clap::{App, Arg};
+use easy_fs::{BlockDevice, EasyFileSystem, BLOCK_SZ};
+use std::fs::{read_dir, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::sync::{Arc, Mutex};
+
+/// Size of the image in blocks, i.e. 16 MiB
+const FS_TOTAL_BLOCKS: u32 = 16 * 2048;
+/// 1 bitmap block for 4096 inodes
+const FS_INODE_BITMAP_BLOCKS: u32 = 1;
+
+/// A host file used as a block device
+struct BlockFile(Mutex<File>);
+
+impl BlockDevice for BlockFile {
+    fn num_blocks(&self) -> usize {
+        let file = self.0.lock().unwrap();
+        file.metadata().unwrap().len() as usize / BLOCK_SZ
+    }
+    fn read_block(&self, block_id: usize, buf: &mut [u8]) {
+        let mut file = self.0.lock().unwrap();
+        file.seek(SeekFrom::Start((block_id * BLOCK_SZ) as u64))
+            .expect("Error when seeking!");
+        file.read_exact(buf).expect("Not a complete block!");
+    }
+    fn write_block(&self, block_id: usize, buf: &[u8]) {
+        let mut file = self.0.lock().unwrap();
+        file.seek(SeekFrom::Start((block_id * BLOCK_SZ) as u64))
+            .expect("Error when seeking!");
+        file.write_all(buf).expect("Not a complete block!");
+    }
+}
+
+fn main() {
+    easy_fs_pack().expect("Error when packing easy-fs!");
+}
+
+fn easy_fs_pack() -> std::io::Result<()> {
+    let matches = App::new("EasyFileSystem packer")
+        .arg(
+            Arg::with_name("source")
+                .short("s")
+                .long("source")
+                .takes_value(true)
+                .required(true)
+                .help("Executable source dir(with backslash)"),
+        )
+        .arg(
+            Arg::with_name("target")
+                .short("t")
+                .long("target")
+                .takes_value(true)
+                .required(true)
+                .help("Executable target dir(with backslash)"),
+        )
+        .get_matches();
+    let src_path = matches.value_of("source").unwrap();
+    let target_path = matches.value_of("target").unwrap();
+    println!("src_path = {}\ntarget_path = {}", src_path, target_path);
+    let block_file = Arc::new(BlockFile(Mutex::new({
+        let f = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(format!("{}{}", target_path, "fs.img"))?;
+        f.set_len(FS_TOTAL_BLOCKS as u64 * BLOCK_SZ as u64)?;
+        f
+    })));
+    let efs = EasyFileSystem::create(block_file, FS_TOTAL_BLOCKS, FS_INODE_BITMAP_BLOCKS);
+    let root_inode = Arc::new(EasyFileSystem::root_inode(&efs));
+    let mut apps: Vec<_> = read_dir(src_path)?
+        .map(|dir_entry| {
+            let mut name_with_ext = dir_entry.unwrap().file_name().into_string().unwrap();
+            name_with_ext.drain(name_with_ext.find('.').unwrap()..name_with_ext.len());
+            name_with_ext
+        })
+        .collect();
+    apps.sort();
+    for app in apps {
+        // load app data from host file system
+        let mut host_file = File::open(format!("{}{}", target_path, app))?;
+        let mut all_data: Vec<u8> = Vec::new();
+        host_file.read_to_end(&mut all_data)?;
+        // create a file in easy-fs
+        let inode = root_inode
+            .create(app.as_str())
+            .unwrap_or_else(|| panic!("Cannot create {} in easy-fs!", app));
+        // write data to easy-fs
+        assert_eq!(
+            inode.write_at(0, all_data.as_slice()),
+            all_data.len(),
+            "No space left for {} in easy-fs!",
+            app
+        );
+    }
+    // list apps
+    for app in root_inode.ls() {
+        println!("{}", app);
+    }
+    Ok(())
+}
@@ -0,0 +1,75 @@
+//! Bitmaps tracking free inodes and data blocks
+use super::{get_block_cache, BlockDevice, BLOCK_SZ};
+
+/// Number of bits in a block
+pub const BLOCK_BITS: usize = BLOCK_SZ * 8;
+use alloc::sync::Arc;
+
+/// A bitmap block, seen as 64 groups of 64 bits
+type BitmapBlock = [u64; 64];
+
+/// A bitmap of `bits` bits taking `blocks` blocks from `start_block_id`
+pub struct Bitmap {
+    start_block_id: usize,
+    blocks: usize,
+    bits: usize,
+}
+
+/// Split a bit number into (block, group, bit in group)
+fn decomposition(mut bit: usize) -> (usize, usize, usize) {
+    let block_pos = bit / BLOCK_BITS;
+    bit %= BLOCK_BITS;
+    (block_pos, bit / 64, bit % 64)
+}
+
+impl Bitmap {
+    /// Create a bitmap, its blocks are not touched. The last block may be
+    /// partly used when `bits` is less than what `blocks` blocks can hold.
+    pub fn new(start_block_id: usize, blocks: usize, bits: usize) -> Self {
+        assert!(bits <= blocks * BLOCK_BITS);
+        Self {
+            start_block_id,
+            blocks,
+            bits,
+        }
+    }
+    /// Allocate the first free bit
+    pub fn alloc(&self, block_device: &Arc<dyn BlockDevice>) -> Option<usize> {
+        for block_id in 0..self.blocks {
+            let pos = get_block_cache(
+                block_id + self.start_block_id,
+                Arc::clone(block_device),
+            )
+            .lock()
+            .modify(0, |bitmap_block: &mut BitmapBlock| {
+                let (bits64_pos, inner_pos) = bitmap_block
+                    .iter()
+                    .enumerate()
+                    .find(|(_, bits64)| **bits64 != u64::MAX)
+                    .map(|(bits64_pos, bits64)| (bits64_pos, bits64.trailing_ones() as usize))?;
+                let bit = block_id * BLOCK_BITS + bits64_pos * 64 + inner_pos;
+                // the rest of the bitmap is not backed by real objects
+                if bit >= self.bits {
+                    return None;
+                }
+                // modify cache
+                bitmap_block[bits64_pos] |= 1u64 << inner_pos;
+                Some(bit)
+            });
+            if pos.is_some() {
+                return pos;
+            }
+        }
+        None
+    }
+    /// Free an allocated bit
+    pub fn dealloc(&self, block_device: &Arc<dyn BlockDevice>, bit: usize) {
+        let (block_pos, bits64_pos, inner_pos) = decomposition(bit);
+        get_block_cache(block_pos + self.start_block_id, Arc::clone(block_device))
+            .lock()
+            .modify(0, |bitmap_block: &mut BitmapBlock| {
+                assert!(bitmap_block[bits64_pos] & (1u64 << inner_pos) > 0);
+                bitmap_block[bits64_pos] -= 1u64 << inner_pos;
+            });
+    }
+}
@@ -0,0 +1,159 @@
+//! Cache of disk blocks
+//!
+//! Every access to the disk goes through a [`BlockCache`], which is written
+//! back when it is evicted or synced. At most [`BLOCK_CACHE_SIZE`] blocks are
+//! cached, the oldest one that is not in use is evicted first.
+use super::{BlockDevice, BLOCK_SZ};
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+use lazy_static::*;
+use spin::Mutex;
+
+/// The bytes of a block, aligned so that on-disk structures can be
+/// referenced in place
+#[repr(C, align(8))]
+struct BlockData([u8; BLOCK_SZ]);
+
+/// A block in memory
+pub struct BlockCache {
+    cache: BlockData,
+    block_id: usize,
+    block_device: Arc<dyn BlockDevice>,
+    modified: bool,
+}
+
+impl BlockCache {
+    /// Load a block from the disk
+    pub fn new(block_id: usize, block_device: Arc<dyn BlockDevice>) -> Self {
+        let mut cache = BlockData([0u8; BLOCK_SZ]);
+        block_device.read_block(block_id, &mut cache.0);
+        Self {
+            cache,
+            block_id,
+            block_device,
+            modified: false,
+        }
+    }
+    fn addr_of_offset(&self, offset: usize) -> usize {
+        &self.cache.0[offset] as *const _ as usize
+    }
+    /// Get a reference to the `T` at `offset` of the block
+    pub fn get_ref<T>(&self, offset: usize) -> &T
+    where
+        T: Sized,
+    {
+        let type_size = core::mem::size_of::<T>();
+        assert!(offset + type_size <= BLOCK_SZ);
+        let addr = self.addr_of_offset(offset);
+        unsafe { &*(addr as *const T) }
+    }
+    /// Get a mutable reference to the `T` at `offset` of the block, the
+    /// block will be written back
+    pub fn get_mut<T>(&mut self, offset: usize) -> &mut T
+    where
+        T: Sized,
+    {
+        let type_size = core::mem::size_of::<T>();
+        assert!(offset + type_size <= BLOCK_SZ);
+        self.modified = true;
+        let addr = self.addr_of_offset(offset);
+        unsafe { &mut *(addr as *mut T) }
+    }
+    /// Call `f` with the `T` at `offset`
+    pub fn read<T, V>(&self, offset: usize, f: impl FnOnce(&T) -> V) -> V {
+        f(self.get_ref(offset))
+    }
+    /// Call `f` with the `T` at `offset`, which may be modified
+    pub fn modify<T, V>(&mut self, offset: usize, f: impl FnOnce(&mut T) -> V) -> V {
+        f(self.get_mut(offset))
+    }
+    /// Write the block back if it has been modified
+    pub fn sync(&mut self) {
+        if self.modified {
+            self.modified = false;
+            self.block_device.write_block(self.block_id, &self.cache.0);
+        }
+    }
+}
+
+impl Drop for BlockCache {
+    fn drop(&mut self) {
+        self.sync()
+    }
+}
+
+/// Max number of blocks in the cache
+const BLOCK_CACHE_SIZE: usize = 16;
+
+/// The blocks in the cache, oldest first
+pub struct BlockCacheManager {
+    queue: VecDeque<(usize, Arc<Mutex<BlockCache>>)>,
+}
+
+impl BlockCacheManager {
+    /// Create an empty cache
+    pub fn new() -> Self {
+        Self {
+            queue: VecDeque::new(),
+        }
+    }
+    /// Get the cached block `block_id`, loading it if needed
+    pub fn get_block_cache(
+        &mut self,
+        block_id: usize,
+        block_device: Arc<dyn BlockDevice>,
+    ) -> Arc<Mutex<BlockCache>> {
+        if let Some(pair) = self.queue.iter().find(|pair| pair.0 == block_id) {
+            return Arc::clone(&pair.1);
+        }
+        if self.queue.len() == BLOCK_CACHE_SIZE {
+            // evict the oldest block which is not in use
+            if let Some((idx, _)) = self
+                .queue
+                .iter()
+                .enumerate()
+                .find(|(_, pair)| Arc::strong_count(&pair.1) == 1)
+            {
+                self.queue.drain(idx..=idx);
+            } else {
+                panic!("Run out of BlockCache!");
+            }
+        }
+        let block_cache = Arc::new(Mutex::new(BlockCache::new(
+            block_id,
+            Arc::clone(&block_device),
+        )));
+        self.queue.push_back((block_id, Arc::clone(&block_cache)));
+        block_cache
+    }
+}
+
+impl Default for BlockCacheManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+lazy_static! {
+    /// The global block cache
+    pub static ref BLOCK_CACHE_MANAGER: Mutex<BlockCacheManager> =
+        Mutex::new(BlockCacheManager::new());
+}
+
+/// Get the cached block `block_id` of `block_device`
+pub fn get_block_cache(
+    block_id: usize,
+    block_device: Arc<dyn BlockDevice>,
+) -> Arc<Mutex<BlockCache>> {
+    BLOCK_CACHE_MANAGER
+        .lock()
+        .get_block_cache(block_id, block_device)
+}
+
+/// Write all modified blocks back to the disk
+pub fn block_cache_sync_all() {
+    let manager = BLOCK_CACHE_MANAGER.lock();
+    for (_, cache) in manager.queue.iter() {
+        cache.lock().sync();
+    }
+}
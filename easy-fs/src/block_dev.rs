@@ -0,0 +1,12 @@
+//! The interface of a block device
+use core::any::Any;
+
+/// A device that is read and written in blocks of [`crate::BLOCK_SZ`] bytes
+pub trait BlockDevice: Send + Sync + Any {
+    /// Number of blocks on the device
+    fn num_blocks(&self) -> usize;
+    /// Read the block `block_id` into `buf`, which holds exactly one block
+    fn read_block(&self, block_id: usize, buf: &mut [u8]);
+    /// Write `buf`, which holds exactly one block, to the block `block_id`
+    fn write_block(&self, block_id: usize, buf: &[u8]);
+}
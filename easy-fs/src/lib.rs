@@ -0,0 +1,33 @@
+//! A simple file system shared by the kernel and the host-side packer
+//!
+//! The disk is laid out in 512-byte blocks as
+//!
+//! ```text
+//! | super block | inode bitmap | inode area | data bitmap | data area |
+//! ```
+//!
+//! - [`BlockDevice`]: the interface the file system needs from a disk
+//! - block cache: keeps recently used blocks in memory
+//! - layout: on-disk structures, i.e. super block, inodes and directory entries
+//! - [`EasyFileSystem`]: allocates inodes and data blocks
+//! - [`Inode`]: files and directories as seen by the users of the crate
+#![no_std]
+
+extern crate alloc;
+
+mod bitmap;
+mod block_cache;
+mod block_dev;
+mod efs;
+mod layout;
+mod vfs;
+
+/// Size of a block in bytes
+pub const BLOCK_SZ: usize = 512;
+use bitmap::{Bitmap, BLOCK_BITS};
+use block_cache::{block_cache_sync_all, get_block_cache};
+pub use block_dev::BlockDevice;
+pub use efs::EasyFileSystem;
+pub use layout::MAX_FILE_SIZE;
+use layout::*;
+pub use vfs::Inode;
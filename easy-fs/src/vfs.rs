@@ -0,0 +1,272 @@
+//! Files and directories
+//!
+//! There is only one level of directories: every file lives in the root.
+use super::{
+    block_cache_sync_all, get_block_cache, BlockDevice, DirEntry, DiskInode, DiskInodeType,
+    EasyFileSystem, DIRENT_SZ, MAX_FILE_SIZE, NAME_LENGTH_LIMIT,
+};
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use spin::{Mutex, MutexGuard};
+
+/// An inode in memory, pointing to its [`DiskInode`]
+pub struct Inode {
+    inode_id: u32,
+    block_id: usize,
+    block_offset: usize,
+    fs: Arc<Mutex<EasyFileSystem>>,
+    block_device: Arc<dyn BlockDevice>,
+}
+
+impl Inode {
+    /// Create an in-memory inode for the disk inode `inode_id`, which is at
+    /// the given position
+    pub fn new(
+        inode_id: u32,
+        block_id: u32,
+        block_offset: usize,
+        fs: Arc<Mutex<EasyFileSystem>>,
+        block_device: Arc<dyn BlockDevice>,
+    ) -> Self {
+        Self {
+            inode_id,
+            block_id: block_id as usize,
+            block_offset,
+            fs,
+            block_device,
+        }
+    }
+    fn read_disk_inode<V>(&self, f: impl FnOnce(&DiskInode) -> V) -> V {
+        get_block_cache(self.block_id, Arc::clone(&self.block_device))
+            .lock()
+            .read(self.block_offset, f)
+    }
+    fn modify_disk_inode<V>(&self, f: impl FnOnce(&mut DiskInode) -> V) -> V {
+        get_block_cache(self.block_id, Arc::clone(&self.block_device))
+            .lock()
+            .modify(self.block_offset, f)
+    }
+    /// Find `name` in the directory `disk_inode`, returns the index of its
+    /// entry and its inode id
+    fn find_dirent(&self, name: &str, disk_inode: &DiskInode) -> Option<(usize, u32)> {
+        // assert it is a directory
+        assert!(disk_inode.is_dir());
+        let file_count = (disk_inode.size as usize) / DIRENT_SZ;
+        let mut dirent = DirEntry::empty();
+        for i in 0..file_count {
+            assert_eq!(
+                disk_inode.read_at(DIRENT_SZ * i, dirent.as_bytes_mut(), &self.block_device),
+                DIRENT_SZ,
+            );
+            if dirent.name() == name {
+                return Some((i, dirent.inode_number()));
+            }
+        }
+        None
+    }
+    /// Find `name` in the directory `disk_inode`, returns its inode id
+    fn find_inode_id(&self, name: &str, disk_inode: &DiskInode) -> Option<u32> {
+        self.find_dirent(name, disk_inode)
+            .map(|(_, inode_id)| inode_id)
+    }
+    fn inode_of(&self, inode_id: u32, fs: &MutexGuard<EasyFileSystem>) -> Arc<Inode> {
+        let (block_id, block_offset) = fs.get_disk_inode_pos(inode_id);
+        Arc::new(Self::new(
+            inode_id,
+            block_id,
+            block_offset,
+            self.fs.clone(),
+            self.block_device.clone(),
+        ))
+    }
+    /// Find a file in this directory by name
+    pub fn find(&self, name: &str) -> Option<Arc<Inode>> {
+        // an empty name only matches unused entries
+        if name.is_empty() {
+            return None;
+        }
+        let fs = self.fs.lock();
+        self.read_disk_inode(|disk_inode| {
+            self.find_inode_id(name, disk_inode)
+                .map(|inode_id| self.inode_of(inode_id, &fs))
+        })
+    }
+    /// Grow the disk inode to `new_size` bytes, returns false if the disk
+    /// does not have enough free blocks
+    fn increase_size(
+        &self,
+        new_size: u32,
+        disk_inode: &mut DiskInode,
+        fs: &mut MutexGuard<EasyFileSystem>,
+    ) -> bool {
+        if new_size <= disk_inode.size {
+            return true;
+        }
+        let blocks_needed = disk_inode.blocks_num_needed(new_size);
+        let mut v: Vec<u32> = Vec::new();
+        for _ in 0..blocks_needed {
+            match fs.alloc_data() {
+                Some(block_id) => v.push(block_id),
+                None => {
+                    for block_id in v {
+                        fs.dealloc_data(block_id);
+                    }
+                    return false;
+                }
+            }
+        }
+        disk_inode.increase_size(new_size, v, &self.block_device);
+        true
+    }
+    /// Create a regular file in this directory, `None` if it already exists,
+    /// the name is too long or there is no free inode
+    pub fn create(&self, name: &str) -> Option<Arc<Inode>> {
+        if name.is_empty() || name.len() > NAME_LENGTH_LIMIT {
+            return None;
+        }
+        let mut fs = self.fs.lock();
+        if self
+            .read_disk_inode(|root_inode| self.find_inode_id(name, root_inode))
+            .is_some()
+        {
+            return None;
+        }
+        // create a new file
+        let new_inode_id = fs.alloc_inode()?;
+        // initialize inode
+        let (new_inode_block_id, new_inode_block_offset) = fs.get_disk_inode_pos(new_inode_id);
+        get_block_cache(new_inode_block_id as usize, Arc::clone(&self.block_device))
+            .lock()
+            .modify(new_inode_block_offset, |new_inode: &mut DiskInode| {
+                new_inode.initialize(DiskInodeType::File);
+            });
+        let added = self.modify_disk_inode(|root_inode| {
+            // reuse the entry of an unlinked file, or append a new one
+            let file_count = (root_inode.size as usize) / DIRENT_SZ;
+            let index = self
+                .find_dirent("", root_inode)
+                .map_or(file_count, |(index, _)| index);
+            if index == file_count {
+                let new_size = (file_count + 1) * DIRENT_SZ;
+                // increase size
+                if !self.increase_size(new_size as u32, root_inode, &mut fs) {
+                    return false;
+                }
+            }
+            // write dirent
+            let dirent = DirEntry::new(name, new_inode_id);
+            root_inode.write_at(index * DIRENT_SZ, dirent.as_bytes(), &self.block_device);
+            true
+        });
+        if !added {
+            fs.dealloc_inode(new_inode_id);
+            return None;
+        }
+        let inode = self.inode_of(new_inode_id, &fs);
+        block_cache_sync_all();
+        // release efs lock automatically by compiler
+        Some(inode)
+    }
+    /// List the names of the files in this directory
+    pub fn ls(&self) -> Vec<String> {
+        let _fs = self.fs.lock();
+        self.read_disk_inode(|disk_inode| {
+            let file_count = (disk_inode.size as usize) / DIRENT_SZ;
+            let mut v: Vec<String> = Vec::new();
+            for i in 0..file_count {
+                let mut dirent = DirEntry::empty();
+                assert_eq!(
+                    disk_inode.read_at(i * DIRENT_SZ, dirent.as_bytes_mut(), &self.block_device,),
+                    DIRENT_SZ,
+                );
+                // skip the entries of unlinked files
+                if !dirent.name().is_empty() {
+                    v.push(String::from(dirent.name()));
+                }
+            }
+            v
+        })
+    }
+    /// Read from `offset` into `buf`, returns the number of bytes read
+    pub fn read_at(&self, offset: usize, buf: &mut [u8]) -> usize {
+        let _fs = self.fs.lock();
+        self.read_disk_inode(|disk_inode| disk_inode.read_at(offset, buf, &self.block_device))
+    }
+    /// Write `buf` at `offset`, growing the file as needed. Returns the number
+    /// of bytes written, which is less than `buf.len()` if the disk is full or
+    /// the write would go past [`MAX_FILE_SIZE`].
+    pub fn write_at(&self, offset: usize, buf: &[u8]) -> usize {
+        if offset >= MAX_FILE_SIZE {
+            return 0;
+        }
+        let buf = &buf[..buf.len().min(MAX_FILE_SIZE - offset)];
+        let mut fs = self.fs.lock();
+        let size = self.modify_disk_inode(|disk_inode| {
+            if !self.increase_size((offset + buf.len()) as u32, disk_inode, &mut fs) {
+                // only overwrite what is already there
+                let end = disk_inode.size as usize;
+                if offset >= end {
+                    return 0;
+                }
+                return disk_inode.write_at(offset, &buf[..end - offset], &self.block_device);
+            }
+            disk_inode.write_at(offset, buf, &self.block_device)
+        });
+        block_cache_sync_all();
+        size
+    }
+    /// Remove `name` from this directory and return its inode, which still
+    /// holds its data until [`Inode::destroy`] is called
+    pub fn unlink(&self, name: &str) -> Option<Arc<Inode>> {
+        if name.is_empty() {
+            return None;
+        }
+        let fs = self.fs.lock();
+        let inode = self.modify_disk_inode(|root_inode| {
+            let (index, inode_id) = self.find_dirent(name, root_inode)?;
+            // an empty name marks the entry as unused
+            root_inode.write_at(
+                index * DIRENT_SZ,
+                DirEntry::empty().as_bytes(),
+                &self.block_device,
+            );
+            Some(self.inode_of(inode_id, &fs))
+        });
+        block_cache_sync_all();
+        inode
+    }
+    /// Free the data blocks and the inode of an unlinked file
+    pub fn destroy(&self) {
+        self.clear();
+        self.fs.lock().dealloc_inode(self.inode_id);
+        block_cache_sync_all();
+    }
+    /// Id of the inode
+    pub fn inode_id(&self) -> u32 {
+        self.inode_id
+    }
+    /// Whether it is a directory
+    pub fn is_dir(&self) -> bool {
+        let _fs = self.fs.lock();
+        self.read_disk_inode(|disk_inode| disk_inode.is_dir())
+    }
+    /// Size of the file in bytes
+    pub fn size(&self) -> usize {
+        let _fs = self.fs.lock();
+        self.read_disk_inode(|disk_inode| disk_inode.size as usize)
+    }
+    /// Truncate the file to 0 bytes and free its blocks
+    pub fn clear(&self) {
+        let mut fs = self.fs.lock();
+        self.modify_disk_inode(|disk_inode| {
+            let size = disk_inode.size;
+            let data_blocks_dealloc = disk_inode.clear_size(&self.block_device);
+            assert!(data_blocks_dealloc.len() == DiskInode::total_blocks(size) as usize);
+            for data_block in data_blocks_dealloc.into_iter() {
+                fs.dealloc_data(data_block);
+            }
+        });
+        block_cache_sync_all();
+    }
+}
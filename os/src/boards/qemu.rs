@@ -3,6 +3,13 @@
 pub const CLOCK_FREQ: usize = 12500000;
 pub const MEMORY_END: usize = 0x8800_0000;
 
+/// the first virtio-mmio slot, where `-device virtio-blk-device` is attached
+pub const VIRTIO0: usize = 0x1000_1000;
+/// the second virtio-mmio slot, where the swap device is attached
+pub const VIRTIO1: usize = 0x1000_2000;
+
 pub const MMIO: &[(usize, usize)] = &[
     (0x0010_0000, 0x00_2000), // VIRT_TEST/RTC  in virt machine
+    (VIRTIO0, 0x00_1000),     // VIRTIO0 in virt machine
+    (VIRTIO1, 0x00_1000),     // VIRTIO1 in virt machine
 ];
@@ -1,12 +1,22 @@
 //! Constants used in rCore
 pub const USER_STACK_SIZE: usize = 4096 * 2;
 pub const KERNEL_STACK_SIZE: usize = 4096 * 2;
+/// initial size of the kernel heap, it grows with frames when it runs out
 pub const KERNEL_HEAP_SIZE: usize = 0x20_0000;
+pub const MAX_FD_NUM: usize = 128;
 
 pub const PAGE_SIZE: usize = 0x1000;
 pub const PAGE_SIZE_BITS: usize = 0xc;
 
 pub const TRAMPOLINE: usize = usize::MAX - PAGE_SIZE + 1;
 pub const TRAP_CONTEXT: usize = TRAMPOLINE - PAGE_SIZE;
+/// User space lives in the lower half of SV39, higher addresses are never valid
+pub const USER_SPACE_END: usize = 1 << 38;
+/// Where `mmap` starts looking for free space when no address is given
+pub const MMAP_BASE: usize = 0x10_0000_0000;
+/// User stacks are far above the heap, which grows up from the end of the ELF.
+/// The stacks of the threads follow each other up from here, so neither the
+/// heap nor `mmap` go past it.
+pub const USER_STACK_BASE: usize = 0x20_0000_0000;
 
-pub use crate::board::{CLOCK_FREQ, MEMORY_END, MMIO};
+pub use crate::board::{CLOCK_FREQ, MEMORY_END, MMIO, VIRTIO0, VIRTIO1};
@@ -0,0 +1,55 @@
+//! Block devices
+mod virtio_blk;
+
+pub use virtio_blk::{VirtIOBlock, SECTOR_SIZE};
+
+use crate::config::VIRTIO1;
+use alloc::sync::Arc;
+pub use easy_fs::BlockDevice;
+use lazy_static::*;
+use virtio_blk::virtio_blk_present;
+
+lazy_static! {
+    /// The block device holding the file system
+    pub static ref BLOCK_DEVICE: Arc<dyn BlockDevice> = Arc::new(VirtIOBlock::new());
+    /// The block device pages are swapped out to, if one is attached
+    pub static ref SWAP_DEVICE: Option<Arc<dyn BlockDevice>> = if virtio_blk_present(VIRTIO1) {
+        Some(Arc::new(VirtIOBlock::with_base(VIRTIO1)))
+    } else {
+        None
+    };
+}
+
+/// Probe the block devices
+pub fn init() {
+    println!(
+        "[kernel] virtio-blk: {} blocks of {} bytes",
+        BLOCK_DEVICE.num_blocks(),
+        SECTOR_SIZE
+    );
+    if let Some(swap_device) = SWAP_DEVICE.as_ref() {
+        println!(
+            "[kernel] swap: {} blocks of {} bytes",
+            swap_device.num_blocks(),
+            SECTOR_SIZE
+        );
+    } else {
+        println!("[kernel] swap: no device, pages are never swapped out");
+    }
+}
+
+#[allow(unused)]
+/// a simple test for the block device, it overwrites the last block
+pub fn block_device_test() {
+    let block_device = BLOCK_DEVICE.clone();
+    let block_id = block_device.num_blocks() - 1;
+    let mut write_buffer = [0u8; SECTOR_SIZE];
+    let mut read_buffer = [0u8; SECTOR_SIZE];
+    for (i, byte) in write_buffer.iter_mut().enumerate() {
+        *byte = i as u8;
+    }
+    block_device.write_block(block_id, &write_buffer);
+    block_device.read_block(block_id, &mut read_buffer);
+    assert_eq!(write_buffer, read_buffer);
+    println!("block_device_test passed!");
+}
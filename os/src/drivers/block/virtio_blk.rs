@@ -0,0 +1,353 @@
+//! Driver for the virtio-mmio block device of the QEMU `virt` machine
+//!
+//! Both the legacy (version 1) and the modern (version 2) register layouts
+//! are supported. There is a single virtqueue, and one request is in flight
+//! at a time: it is made of three descriptors (header, data and status) that
+//! all live in one DMA page, and it is completed by polling the used ring, so
+//! no external interrupt is needed.
+//!
+//! The kernel maps physical memory identically, so the physical address of a
+//! frame is also the address the kernel uses to access it.
+use super::BlockDevice;
+use crate::config::{PAGE_SIZE, VIRTIO0};
+use crate::mm::{frame_alloc, frame_alloc_contiguous, FrameTracker, PhysAddr};
+use crate::sync::UPSafeCell;
+use alloc::vec::Vec;
+use core::hint::spin_loop;
+use core::mem::size_of;
+use core::ptr::{read_volatile, write_volatile};
+use core::sync::atomic::{fence, Ordering};
+
+/// Size of a sector, which is also the size of a block
+pub const SECTOR_SIZE: usize = 512;
+
+/// Number of descriptors in the virtqueue, a request needs 3 of them
+const QUEUE_SIZE: usize = 8;
+
+/// "virt" in little endian
+const VIRTIO_MAGIC: u32 = 0x7472_6976;
+const VIRTIO_DEVICE_ID_BLOCK: u32 = 2;
+/// feature bit 32, i.e. bit 0 of the second feature word, must be accepted
+/// by drivers of modern devices
+const VIRTIO_F_VERSION_1: u32 = 1;
+
+// offsets of the virtio-mmio registers
+const REG_MAGIC_VALUE: usize = 0x000;
+const REG_VERSION: usize = 0x004;
+const REG_DEVICE_ID: usize = 0x008;
+const REG_DEVICE_FEATURES: usize = 0x010;
+const REG_DEVICE_FEATURES_SEL: usize = 0x014;
+const REG_DRIVER_FEATURES: usize = 0x020;
+const REG_DRIVER_FEATURES_SEL: usize = 0x024;
+const REG_GUEST_PAGE_SIZE: usize = 0x028;
+const REG_QUEUE_SEL: usize = 0x030;
+const REG_QUEUE_NUM_MAX: usize = 0x034;
+const REG_QUEUE_NUM: usize = 0x038;
+const REG_QUEUE_ALIGN: usize = 0x03c;
+const REG_QUEUE_PFN: usize = 0x040;
+const REG_QUEUE_READY: usize = 0x044;
+const REG_QUEUE_NOTIFY: usize = 0x050;
+const REG_INTERRUPT_STATUS: usize = 0x060;
+const REG_INTERRUPT_ACK: usize = 0x064;
+const REG_STATUS: usize = 0x070;
+const REG_QUEUE_DESC_LOW: usize = 0x080;
+const REG_QUEUE_DESC_HIGH: usize = 0x084;
+const REG_QUEUE_DRIVER_LOW: usize = 0x090;
+const REG_QUEUE_DRIVER_HIGH: usize = 0x094;
+const REG_QUEUE_DEVICE_LOW: usize = 0x0a0;
+const REG_QUEUE_DEVICE_HIGH: usize = 0x0a4;
+/// `capacity` of the block device config space, in sectors
+const REG_CONFIG_CAPACITY: usize = 0x100;
+
+// bits of the device status register
+const STATUS_ACKNOWLEDGE: u32 = 1;
+const STATUS_DRIVER: u32 = 2;
+const STATUS_DRIVER_OK: u32 = 4;
+const STATUS_FEATURES_OK: u32 = 8;
+const STATUS_FAILED: u32 = 128;
+
+// flags of a descriptor
+const DESC_F_NEXT: u16 = 1;
+const DESC_F_WRITE: u16 = 2;
+
+// types and status of a block request
+const BLK_T_IN: u32 = 0;
+const BLK_T_OUT: u32 = 1;
+const BLK_S_OK: u8 = 0;
+
+/// A descriptor of the virtqueue
+#[repr(C)]
+struct Descriptor {
+    addr: u64,
+    len: u32,
+    flags: u16,
+    next: u16,
+}
+
+/// The header of a block request
+#[repr(C)]
+struct BlockRequestHeader {
+    request_type: u32,
+    reserved: u32,
+    sector: u64,
+}
+
+// layout of the virtqueue, which takes two pages: the descriptor table is
+// followed by the available ring, the used ring starts on the next page
+const AVAIL_OFFSET: usize = QUEUE_SIZE * size_of::<Descriptor>();
+const USED_OFFSET: usize = PAGE_SIZE;
+const QUEUE_PAGES: usize = 2;
+
+// layout of the request page
+const REQ_HEADER_OFFSET: usize = 0;
+const REQ_STATUS_OFFSET: usize = size_of::<BlockRequestHeader>();
+const REQ_DATA_OFFSET: usize = SECTOR_SIZE;
+
+/// Allocate `pages` physically contiguous frames for DMA
+fn dma_alloc(pages: usize) -> Vec<FrameTracker> {
+    frame_alloc_contiguous(pages, 1).expect("virtio-blk: out of memory")
+}
+
+/// Address of the first byte of a frame
+fn frame_addr(frame: &FrameTracker) -> usize {
+    PhysAddr::from(frame.ppn).into()
+}
+
+/// The virtio block device
+pub struct VirtIOBlock {
+    inner: UPSafeCell<VirtIOBlockInner>,
+}
+
+struct VirtIOBlockInner {
+    /// base address of the MMIO registers
+    base: usize,
+    /// number of sectors
+    capacity: usize,
+    /// frames of the virtqueue
+    queue_frames: Vec<FrameTracker>,
+    /// frame holding the header, status and data of the request
+    req_frame: FrameTracker,
+    /// index of the next entry of the available ring
+    avail_idx: u16,
+    /// index of the next entry of the used ring
+    used_idx: u16,
+}
+
+impl VirtIOBlockInner {
+    fn read_reg(&self, offset: usize) -> u32 {
+        unsafe { read_volatile((self.base + offset) as *const u32) }
+    }
+    fn write_reg(&self, offset: usize, value: u32) {
+        unsafe { write_volatile((self.base + offset) as *mut u32, value) }
+    }
+    fn write_reg64(&self, low: usize, high: usize, value: usize) {
+        self.write_reg(low, value as u32);
+        self.write_reg(high, (value >> 32) as u32);
+    }
+    fn queue_addr(&self) -> usize {
+        frame_addr(&self.queue_frames[0])
+    }
+    fn req_addr(&self) -> usize {
+        frame_addr(&self.req_frame)
+    }
+    fn desc(&self, i: usize) -> *mut Descriptor {
+        (self.queue_addr() + i * size_of::<Descriptor>()) as *mut Descriptor
+    }
+    /// Reset and set up the device, following the initialization sequence
+    /// of the virtio spec
+    fn init(&mut self) {
+        assert_eq!(
+            self.read_reg(REG_MAGIC_VALUE),
+            VIRTIO_MAGIC,
+            "virtio-blk: no virtio device at {:#x}",
+            self.base
+        );
+        let version = self.read_reg(REG_VERSION);
+        assert!(
+            version == 1 || version == 2,
+            "virtio-blk: unsupported version {}",
+            version
+        );
+        assert_eq!(
+            self.read_reg(REG_DEVICE_ID),
+            VIRTIO_DEVICE_ID_BLOCK,
+            "virtio-blk: no block device at {:#x}, is the disk image attached?",
+            self.base
+        );
+        self.write_reg(REG_STATUS, 0);
+        let mut status = STATUS_ACKNOWLEDGE | STATUS_DRIVER;
+        self.write_reg(REG_STATUS, status);
+        // accept no optional features, only VERSION_1 for a modern device
+        self.write_reg(REG_DRIVER_FEATURES_SEL, 0);
+        self.write_reg(REG_DRIVER_FEATURES, 0);
+        if version == 2 {
+            self.write_reg(REG_DEVICE_FEATURES_SEL, 1);
+            assert!(self.read_reg(REG_DEVICE_FEATURES) & VIRTIO_F_VERSION_1 != 0);
+            self.write_reg(REG_DRIVER_FEATURES_SEL, 1);
+            self.write_reg(REG_DRIVER_FEATURES, VIRTIO_F_VERSION_1);
+            status |= STATUS_FEATURES_OK;
+            self.write_reg(REG_STATUS, status);
+            if self.read_reg(REG_STATUS) & STATUS_FEATURES_OK == 0 {
+                self.write_reg(REG_STATUS, STATUS_FAILED);
+                panic!("virtio-blk: features are not accepted by the device");
+            }
+        }
+        // set up the only virtqueue
+        self.write_reg(REG_QUEUE_SEL, 0);
+        assert!(self.read_reg(REG_QUEUE_NUM_MAX) as usize >= QUEUE_SIZE);
+        self.write_reg(REG_QUEUE_NUM, QUEUE_SIZE as u32);
+        let queue_addr = self.queue_addr();
+        if version == 1 {
+            self.write_reg(REG_GUEST_PAGE_SIZE, PAGE_SIZE as u32);
+            self.write_reg(REG_QUEUE_ALIGN, PAGE_SIZE as u32);
+            self.write_reg(REG_QUEUE_PFN, (queue_addr / PAGE_SIZE) as u32);
+        } else {
+            self.write_reg64(REG_QUEUE_DESC_LOW, REG_QUEUE_DESC_HIGH, queue_addr);
+            self.write_reg64(
+                REG_QUEUE_DRIVER_LOW,
+                REG_QUEUE_DRIVER_HIGH,
+                queue_addr + AVAIL_OFFSET,
+            );
+            self.write_reg64(
+                REG_QUEUE_DEVICE_LOW,
+                REG_QUEUE_DEVICE_HIGH,
+                queue_addr + USED_OFFSET,
+            );
+            self.write_reg(REG_QUEUE_READY, 1);
+        }
+        // every request uses the same chain: header -> data -> status
+        let req_addr = self.req_addr();
+        unsafe {
+            *self.desc(0) = Descriptor {
+                addr: (req_addr + REQ_HEADER_OFFSET) as u64,
+                len: size_of::<BlockRequestHeader>() as u32,
+                flags: DESC_F_NEXT,
+                next: 1,
+            };
+            *self.desc(1) = Descriptor {
+                addr: (req_addr + REQ_DATA_OFFSET) as u64,
+                len: SECTOR_SIZE as u32,
+                flags: DESC_F_NEXT,
+                next: 2,
+            };
+            *self.desc(2) = Descriptor {
+                addr: (req_addr + REQ_STATUS_OFFSET) as u64,
+                len: 1,
+                flags: DESC_F_WRITE,
+                next: 0,
+            };
+        }
+        let capacity_low = self.read_reg(REG_CONFIG_CAPACITY) as usize;
+        let capacity_high = self.read_reg(REG_CONFIG_CAPACITY + 4) as usize;
+        self.capacity = (capacity_high << 32) | capacity_low;
+        status |= STATUS_DRIVER_OK;
+        self.write_reg(REG_STATUS, status);
+    }
+    /// Submit a request for `sector` and wait until the device completes it.
+    /// The data is transferred through the data area of the request page.
+    fn request(&mut self, sector: usize, write: bool) {
+        assert!(
+            sector < self.capacity,
+            "virtio-blk: sector {} is out of range",
+            sector
+        );
+        let req_addr = self.req_addr();
+        let queue_addr = self.queue_addr();
+        unsafe {
+            write_volatile(
+                (req_addr + REQ_HEADER_OFFSET) as *mut BlockRequestHeader,
+                BlockRequestHeader {
+                    request_type: if write { BLK_T_OUT } else { BLK_T_IN },
+                    reserved: 0,
+                    sector: sector as u64,
+                },
+            );
+            write_volatile((req_addr + REQ_STATUS_OFFSET) as *mut u8, u8::MAX);
+            // the device writes to the data area only for a read
+            (*self.desc(1)).flags = if write {
+                DESC_F_NEXT
+            } else {
+                DESC_F_NEXT | DESC_F_WRITE
+            };
+            // avail ring: flags, idx, ring[QUEUE_SIZE]
+            let avail_ring = (queue_addr + AVAIL_OFFSET + 4) as *mut u16;
+            let avail_idx = (queue_addr + AVAIL_OFFSET + 2) as *mut u16;
+            write_volatile(avail_ring.add(self.avail_idx as usize % QUEUE_SIZE), 0);
+            // the entry must be visible before the index is
+            fence(Ordering::SeqCst);
+            self.avail_idx = self.avail_idx.wrapping_add(1);
+            write_volatile(avail_idx, self.avail_idx);
+            fence(Ordering::SeqCst);
+        }
+        self.write_reg(REG_QUEUE_NOTIFY, 0);
+        // used ring: flags, idx, ring[QUEUE_SIZE]
+        let used_idx = (queue_addr + USED_OFFSET + 2) as *const u16;
+        while unsafe { read_volatile(used_idx) } == self.used_idx {
+            spin_loop();
+        }
+        fence(Ordering::SeqCst);
+        self.used_idx = self.used_idx.wrapping_add(1);
+        let interrupt_status = self.read_reg(REG_INTERRUPT_STATUS);
+        self.write_reg(REG_INTERRUPT_ACK, interrupt_status);
+        let status = unsafe { read_volatile((req_addr + REQ_STATUS_OFFSET) as *const u8) };
+        assert_eq!(
+            status, BLK_S_OK,
+            "virtio-blk: request on sector {} failed",
+            sector
+        );
+    }
+    fn data(&self) -> &'static mut [u8] {
+        &mut self.req_frame.ppn.get_bytes_array()[REQ_DATA_OFFSET..REQ_DATA_OFFSET + SECTOR_SIZE]
+    }
+}
+
+impl VirtIOBlock {
+    /// Probe and set up the device at [`VIRTIO0`]
+    pub fn new() -> Self {
+        Self::with_base(VIRTIO0)
+    }
+    /// Probe and set up the device whose registers are at `base`
+    pub fn with_base(base: usize) -> Self {
+        let mut inner = VirtIOBlockInner {
+            base,
+            capacity: 0,
+            queue_frames: dma_alloc(QUEUE_PAGES),
+            req_frame: frame_alloc().expect("virtio-blk: out of memory"),
+            avail_idx: 0,
+            used_idx: 0,
+        };
+        inner.init();
+        Self {
+            inner: unsafe { UPSafeCell::new(inner) },
+        }
+    }
+}
+
+/// Whether a block device is attached to the virtio-mmio slot at `base`, an
+/// empty slot reads as device 0
+pub fn virtio_blk_present(base: usize) -> bool {
+    let read_reg = |offset: usize| unsafe { read_volatile((base + offset) as *const u32) };
+    read_reg(REG_MAGIC_VALUE) == VIRTIO_MAGIC && read_reg(REG_DEVICE_ID) == VIRTIO_DEVICE_ID_BLOCK
+}
+
+impl Default for VirtIOBlock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BlockDevice for VirtIOBlock {
+    fn num_blocks(&self) -> usize {
+        self.inner.exclusive_access().capacity
+    }
+    fn read_block(&self, block_id: usize, buf: &mut [u8]) {
+        let mut inner = self.inner.exclusive_access();
+        inner.request(block_id, false);
+        buf.copy_from_slice(inner.data());
+    }
+    fn write_block(&self, block_id: usize, buf: &[u8]) {
+        let mut inner = self.inner.exclusive_access();
+        inner.data().copy_from_slice(buf);
+        inner.request(block_id, true);
+    }
+}
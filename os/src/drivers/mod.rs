@@ -0,0 +1,10 @@
+//! Device drivers
+//!
+//! For now there are only the virtio block devices of the QEMU `virt`
+//! machine, one for the file system and one for swap, see [`block`].
+pub mod block;
+
+/// Probe the devices, so that a missing one is reported at boot time
+pub fn init() {
+    block::init();
+}
@@ -0,0 +1,293 @@
+//! Files on the disk
+//!
+//! The easy-fs on [`BLOCK_DEVICE`] is opened at first use; all files live in
+//! its root directory. A file unlinked while it is still open keeps its data
+//! until the last [`OSInode`] referring to it is dropped.
+use super::{File, PollEvents, Stat, StatMode, SEEK_CUR, SEEK_END, SEEK_SET};
+use crate::drivers::block::BLOCK_DEVICE;
+use crate::mm::UserSlice;
+use crate::sync::UPSafeCell;
+use crate::syscall::{SysError, SysResult};
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use easy_fs::{EasyFileSystem, Inode, MAX_FILE_SIZE};
+use lazy_static::*;
+
+/// Max length of a file name, the limit of easy-fs
+const NAME_LENGTH_LIMIT: usize = 27;
+
+/// A file opened by a process, with its own offset
+pub struct OSInode {
+    readable: bool,
+    writable: bool,
+    append: bool,
+    inner: UPSafeCell<OSInodeInner>,
+}
+
+/// The mutable part of an [`OSInode`]
+pub struct OSInodeInner {
+    offset: usize,
+    inode: Arc<Inode>,
+}
+
+/// How many [`OSInode`]s refer to an inode, and whether it has been unlinked
+struct OpenCount {
+    count: usize,
+    unlinked: bool,
+}
+
+lazy_static! {
+    ///The root directory of the file system
+    pub static ref ROOT_INODE: Arc<Inode> = {
+        let efs = EasyFileSystem::open(BLOCK_DEVICE.clone())
+            .expect("no easy-fs on the block device, is fs.img packed?");
+        Arc::new(EasyFileSystem::root_inode(&efs))
+    };
+    ///Open inodes by inode id
+    static ref OPEN_INODES: UPSafeCell<BTreeMap<u32, OpenCount>> =
+        unsafe { UPSafeCell::new(BTreeMap::new()) };
+}
+
+impl OSInode {
+    ///Create an `OSInode` at offset 0
+    pub fn new(readable: bool, writable: bool, append: bool, inode: Arc<Inode>) -> Self {
+        OPEN_INODES
+            .exclusive_access()
+            .entry(inode.inode_id())
+            .or_insert(OpenCount {
+                count: 0,
+                unlinked: false,
+            })
+            .count += 1;
+        Self {
+            readable,
+            writable,
+            append,
+            inner: unsafe { UPSafeCell::new(OSInodeInner { offset: 0, inode }) },
+        }
+    }
+    ///Read from the current offset to the end of the file, fails with
+    ///`ENOMEM` if the kernel heap can not hold it
+    pub fn read_all(&self) -> SysResult<Vec<u8>> {
+        let mut inner = self.inner.exclusive_access();
+        let mut buffer = [0u8; 512];
+        let mut v: Vec<u8> = Vec::new();
+        loop {
+            let len = inner.inode.read_at(inner.offset, &mut buffer);
+            if len == 0 {
+                break;
+            }
+            inner.offset += len;
+            v.try_reserve(len).map_err(|_| SysError::ENOMEM)?;
+            v.extend_from_slice(&buffer[..len]);
+        }
+        Ok(v)
+    }
+}
+
+impl Drop for OSInode {
+    fn drop(&mut self) {
+        let inner = self.inner.exclusive_access();
+        let inode = &inner.inode;
+        let mut open_inodes = OPEN_INODES.exclusive_access();
+        let open_count = open_inodes.get_mut(&inode.inode_id()).unwrap();
+        open_count.count -= 1;
+        if open_count.count == 0 {
+            let unlinked = open_count.unlinked;
+            open_inodes.remove(&inode.inode_id());
+            drop(open_inodes);
+            // the last reference to an unlinked file is gone
+            if unlinked {
+                inode.destroy();
+            }
+        }
+    }
+}
+
+bitflags! {
+    ///Flags of `open`
+    pub struct OpenFlags: u32 {
+        ///Write only
+        const WRONLY = 1 << 0;
+        ///Read and write
+        const RDWR = 1 << 1;
+        ///Create the file if it does not exist
+        const CREATE = 1 << 6;
+        ///Truncate the file to 0 bytes
+        const TRUNC = 1 << 9;
+        ///Every write goes to the end of the file
+        const APPEND = 1 << 10;
+    }
+}
+
+impl OpenFlags {
+    ///Read only, no access mode bit set
+    pub const RDONLY: Self = Self::empty();
+    ///Return (readable, writable), `WRONLY | RDWR` is rejected by `open_file`
+    pub fn read_write(&self) -> (bool, bool) {
+        if self.is_empty() {
+            (true, false)
+        } else if self.contains(Self::WRONLY) {
+            (false, true)
+        } else {
+            (true, true)
+        }
+    }
+}
+
+///List all files in the file system
+pub fn list_apps() {
+    println!("/**** APPS ****");
+    for app in ROOT_INODE.ls() {
+        println!("{}", app);
+    }
+    println!("**************/");
+}
+
+/// Get the name of a file in the root directory from its path
+fn file_name(path: &str) -> SysResult<&str> {
+    let name = path.strip_prefix('/').unwrap_or(path);
+    if name.is_empty() || name.contains('/') {
+        return Err(SysError::ENOENT);
+    }
+    if name.len() > NAME_LENGTH_LIMIT {
+        return Err(SysError::ENAMETOOLONG);
+    }
+    Ok(name)
+}
+
+///Open a file in the root directory
+pub fn open_file(path: &str, flags: OpenFlags) -> SysResult<Arc<OSInode>> {
+    if flags.contains(OpenFlags::WRONLY | OpenFlags::RDWR) {
+        return Err(SysError::EINVAL);
+    }
+    let name = file_name(path)?;
+    let (readable, writable) = flags.read_write();
+    let inode = match ROOT_INODE.find(name) {
+        Some(inode) => {
+            if flags.contains(OpenFlags::TRUNC) && writable {
+                inode.clear();
+            }
+            inode
+        }
+        None if flags.contains(OpenFlags::CREATE) => {
+            // the name is valid, so only the disk can be full
+            ROOT_INODE.create(name).ok_or(SysError::ENOSPC)?
+        }
+        None => return Err(SysError::ENOENT),
+    };
+    Ok(Arc::new(OSInode::new(
+        readable,
+        writable,
+        flags.contains(OpenFlags::APPEND),
+        inode,
+    )))
+}
+
+///Remove a file from the root directory, it is freed once no longer open
+pub fn unlink_file(path: &str) -> SysResult<()> {
+    let name = file_name(path)?;
+    let inode = ROOT_INODE.unlink(name).ok_or(SysError::ENOENT)?;
+    let mut open_inodes = OPEN_INODES.exclusive_access();
+    if let Some(open_count) = open_inodes.get_mut(&inode.inode_id()) {
+        open_count.unlinked = true;
+    } else {
+        drop(open_inodes);
+        inode.destroy();
+    }
+    Ok(())
+}
+
+impl File for OSInode {
+    fn readable(&self) -> bool {
+        self.readable
+    }
+    fn writable(&self) -> bool {
+        self.writable
+    }
+    fn read(&self, buf: UserSlice) -> SysResult<usize> {
+        if !self.readable {
+            return Err(SysError::EBADF);
+        }
+        let mut inner = self.inner.exclusive_access();
+        let mut total_read_size = 0usize;
+        for slice in buf.buffers(true)? {
+            let read_size = inner.inode.read_at(inner.offset, slice);
+            if read_size == 0 {
+                break;
+            }
+            inner.offset += read_size;
+            total_read_size += read_size;
+        }
+        Ok(total_read_size)
+    }
+    fn write(&self, buf: UserSlice) -> SysResult<usize> {
+        if !self.writable {
+            return Err(SysError::EBADF);
+        }
+        let mut inner = self.inner.exclusive_access();
+        if self.append {
+            inner.offset = inner.inode.size();
+        }
+        let mut total_write_size = 0usize;
+        for slice in buf.buffers(false)? {
+            let write_size = inner.inode.write_at(inner.offset, slice);
+            inner.offset += write_size;
+            total_write_size += write_size;
+            if write_size < slice.len() {
+                break;
+            }
+        }
+        if total_write_size == 0 && !buf.is_empty() {
+            return Err(SysError::ENOSPC);
+        }
+        Ok(total_write_size)
+    }
+    fn poll(&self) -> PollEvents {
+        // a disk file never blocks
+        let mut events = PollEvents::empty();
+        if self.readable {
+            events |= PollEvents::IN;
+        }
+        if self.writable {
+            events |= PollEvents::OUT;
+        }
+        events
+    }
+    fn stat(&self) -> Stat {
+        let inner = self.inner.exclusive_access();
+        let inode = &inner.inode;
+        let unlinked = OPEN_INODES
+            .exclusive_access()
+            .get(&inode.inode_id())
+            .map_or(false, |open_count| open_count.unlinked);
+        Stat {
+            dev: 0,
+            ino: inode.inode_id() as u64,
+            mode: if inode.is_dir() {
+                StatMode::DIR
+            } else {
+                StatMode::FILE
+            },
+            nlink: if unlinked { 0 } else { 1 },
+            size: inode.size() as u64,
+        }
+    }
+    fn seek(&self, offset: isize, whence: usize) -> SysResult<usize> {
+        let mut inner = self.inner.exclusive_access();
+        let base = match whence {
+            SEEK_SET => 0,
+            SEEK_CUR => inner.offset,
+            SEEK_END => inner.inode.size(),
+            _ => return Err(SysError::EINVAL),
+        };
+        // easy-fs files cannot grow past `MAX_FILE_SIZE`
+        let new_offset = (base as isize)
+            .checked_add(offset)
+            .filter(|offset| (0..=MAX_FILE_SIZE as isize).contains(offset))
+            .ok_or(SysError::EINVAL)?;
+        inner.offset = new_offset as usize;
+        Ok(inner.offset)
+    }
+}
@@ -0,0 +1,99 @@
+//! File abstraction
+//!
+//! Everything a process can hold in its file descriptor table implements
+//! [`File`]: the console-backed [`Stdin`] and [`Stdout`], [`Pipe`] ends and
+//! [`OSInode`]s, which are files of the easy-fs on the block device.
+mod inode;
+mod pipe;
+mod stdio;
+
+use crate::mm::UserSlice;
+use crate::syscall::{SysError, SysResult};
+
+pub use inode::{list_apps, open_file, unlink_file, OSInode, OpenFlags};
+pub use pipe::{make_pipe, Pipe};
+pub use stdio::{Stdin, Stdout};
+
+bitflags! {
+    /// readiness of a file reported by [`File::poll`]
+    pub struct PollEvents: u16 {
+        ///There is data to read
+        const IN = 1 << 0;
+        ///Writing will not block
+        const OUT = 1 << 2;
+        ///Error condition
+        const ERR = 1 << 3;
+        ///The other end has been closed
+        const HUP = 1 << 4;
+    }
+}
+
+bitflags! {
+    /// type of a file reported by `fstat`
+    pub struct StatMode: u32 {
+        ///Named pipe (FIFO)
+        const FIFO = 0o010000;
+        ///Character device
+        const CHR = 0o020000;
+        ///Directory
+        const DIR = 0o040000;
+        ///Regular file
+        const FILE = 0o100000;
+    }
+}
+
+/// status of a file returned by `fstat`
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct Stat {
+    ///ID of the device containing the file
+    pub dev: u64,
+    ///Inode number
+    pub ino: u64,
+    ///File type
+    pub mode: StatMode,
+    ///Number of hard links
+    pub nlink: u32,
+    ///Size of the file in bytes
+    pub size: u64,
+}
+
+impl Stat {
+    ///Status of a file which is not on the disk
+    pub fn new(mode: StatMode) -> Self {
+        Self {
+            dev: 0,
+            ino: 0,
+            mode,
+            nlink: 1,
+            size: 0,
+        }
+    }
+}
+
+///`File::seek` from the start of the file
+pub const SEEK_SET: usize = 0;
+///`File::seek` from the current offset
+pub const SEEK_CUR: usize = 1;
+///`File::seek` from the end of the file
+pub const SEEK_END: usize = 2;
+
+/// trait for all kinds of files held in a file descriptor table
+pub trait File: Send + Sync {
+    ///Whether the file can be read from
+    fn readable(&self) -> bool;
+    ///Whether the file can be written to
+    fn writable(&self) -> bool;
+    ///Read into the user buffer, returns the number of bytes read
+    fn read(&self, buf: UserSlice) -> SysResult<usize>;
+    ///Write from the user buffer, returns the number of bytes written
+    fn write(&self, buf: UserSlice) -> SysResult<usize>;
+    ///Check which operations can be done without blocking
+    fn poll(&self) -> PollEvents;
+    ///Get the status of the file
+    fn stat(&self) -> Stat;
+    ///Move the offset, returns the new offset. Only files on the disk can seek.
+    fn seek(&self, _offset: isize, _whence: usize) -> SysResult<usize> {
+        Err(SysError::ESPIPE)
+    }
+}
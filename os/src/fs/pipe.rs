@@ -0,0 +1,225 @@
+//! Anonymous pipes
+//!
+//! Both ends of a pipe share a ring buffer. A reader blocks while the buffer
+//! is empty and a writer blocks while it is full; each side wakes the other
+//! after making progress. Once every descriptor of the write end is closed,
+//! reads return 0 (EOF); once the read end is gone, writes fail with `EPIPE`.
+use super::{File, PollEvents, Stat, StatMode};
+use crate::mm::UserSlice;
+use crate::sync::UPSafeCell;
+use crate::syscall::{SysError, SysResult};
+use crate::task::{block_current_and_run_next, current_task, wakeup_task, ThreadControlBlock};
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+
+/// Capacity of the ring buffer of a pipe in bytes
+const RING_BUFFER_SIZE: usize = 512;
+
+/// One end of a pipe
+pub struct Pipe {
+    readable: bool,
+    writable: bool,
+    buffer: Arc<UPSafeCell<PipeRingBuffer>>,
+}
+
+impl Pipe {
+    /// Create the read end of a pipe with given buffer
+    fn read_end_with_buffer(buffer: Arc<UPSafeCell<PipeRingBuffer>>) -> Self {
+        Self {
+            readable: true,
+            writable: false,
+            buffer,
+        }
+    }
+    /// Create the write end of a pipe with given buffer
+    fn write_end_with_buffer(buffer: Arc<UPSafeCell<PipeRingBuffer>>) -> Self {
+        Self {
+            readable: false,
+            writable: true,
+            buffer,
+        }
+    }
+}
+
+struct PipeRingBuffer {
+    arr: [u8; RING_BUFFER_SIZE],
+    head: usize,
+    len: usize,
+    read_end_closed: bool,
+    write_end_closed: bool,
+    /// threads blocked on an empty buffer
+    read_waiters: VecDeque<Arc<ThreadControlBlock>>,
+    /// threads blocked on a full buffer
+    write_waiters: VecDeque<Arc<ThreadControlBlock>>,
+}
+
+impl PipeRingBuffer {
+    fn new() -> Self {
+        Self {
+            arr: [0; RING_BUFFER_SIZE],
+            head: 0,
+            len: 0,
+            read_end_closed: false,
+            write_end_closed: false,
+            read_waiters: VecDeque::new(),
+            write_waiters: VecDeque::new(),
+        }
+    }
+    fn available_write(&self) -> usize {
+        RING_BUFFER_SIZE - self.len
+    }
+    /// Copy out as many bytes as `dst` holds or the buffer has, leaving them
+    /// in the buffer
+    fn peek_bytes(&self, dst: &mut [u8]) -> usize {
+        let n = dst.len().min(self.len);
+        for (i, byte) in dst[..n].iter_mut().enumerate() {
+            *byte = self.arr[(self.head + i) % RING_BUFFER_SIZE];
+        }
+        n
+    }
+    /// Drop the first `n` bytes, which have been read
+    fn consume(&mut self, n: usize) {
+        self.head = (self.head + n) % RING_BUFFER_SIZE;
+        self.len -= n;
+    }
+    /// Copy in as many bytes of `src` as there is room for
+    fn write_bytes(&mut self, src: &[u8]) -> usize {
+        let n = src.len().min(self.available_write());
+        for &byte in src[..n].iter() {
+            self.arr[(self.head + self.len) % RING_BUFFER_SIZE] = byte;
+            self.len += 1;
+        }
+        n
+    }
+}
+
+/// Wake up all threads in `waiters`
+fn wakeup_all(waiters: VecDeque<Arc<ThreadControlBlock>>) {
+    for thread in waiters {
+        wakeup_task(thread);
+    }
+}
+
+/// Create a pipe and return its (read end, write end)
+pub fn make_pipe() -> (Arc<Pipe>, Arc<Pipe>) {
+    let buffer = Arc::new(unsafe { UPSafeCell::new(PipeRingBuffer::new()) });
+    let read_end = Arc::new(Pipe::read_end_with_buffer(buffer.clone()));
+    let write_end = Arc::new(Pipe::write_end_with_buffer(buffer));
+    (read_end, write_end)
+}
+
+impl File for Pipe {
+    fn readable(&self) -> bool {
+        self.readable
+    }
+    fn writable(&self) -> bool {
+        self.writable
+    }
+    /// Block until some data is available, then read what fits in `buf`
+    fn read(&self, buf: UserSlice) -> SysResult<usize> {
+        if !self.readable {
+            return Err(SysError::EBADF);
+        }
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        // fail early rather than block on a bad buffer
+        buf.buffers(true)?;
+        loop {
+            let mut ring_buffer = self.buffer.exclusive_access();
+            if ring_buffer.len == 0 {
+                if ring_buffer.write_end_closed {
+                    return Ok(0);
+                }
+                ring_buffer.read_waiters.push_back(current_task().unwrap());
+                drop(ring_buffer);
+                block_current_and_run_next();
+                continue;
+            }
+            let mut data = [0u8; RING_BUFFER_SIZE];
+            let n = ring_buffer.peek_bytes(&mut data[..buf.len().min(RING_BUFFER_SIZE)]);
+            drop(ring_buffer);
+            // the buffer may have been unmapped while this thread was blocked,
+            // the data stays in the pipe then
+            let n = buf.copy_to_user(&data[..n])?;
+            let mut ring_buffer = self.buffer.exclusive_access();
+            ring_buffer.consume(n);
+            let waiters = core::mem::take(&mut ring_buffer.write_waiters);
+            drop(ring_buffer);
+            wakeup_all(waiters);
+            return Ok(n);
+        }
+    }
+    /// Write the whole `buf`, blocking whenever the pipe is full
+    fn write(&self, buf: UserSlice) -> SysResult<usize> {
+        if !self.writable {
+            return Err(SysError::EBADF);
+        }
+        let data = buf.copy_from_user()?;
+        let mut written = 0;
+        while written < data.len() {
+            let mut ring_buffer = self.buffer.exclusive_access();
+            if ring_buffer.read_end_closed {
+                // report a partial write, the next write gets the error
+                return if written > 0 {
+                    Ok(written)
+                } else {
+                    Err(SysError::EPIPE)
+                };
+            }
+            if ring_buffer.available_write() == 0 {
+                ring_buffer.write_waiters.push_back(current_task().unwrap());
+                drop(ring_buffer);
+                block_current_and_run_next();
+                continue;
+            }
+            written += ring_buffer.write_bytes(&data[written..]);
+            let waiters = core::mem::take(&mut ring_buffer.read_waiters);
+            drop(ring_buffer);
+            wakeup_all(waiters);
+        }
+        Ok(written)
+    }
+    fn poll(&self) -> PollEvents {
+        let ring_buffer = self.buffer.exclusive_access();
+        let mut events = PollEvents::empty();
+        if self.readable {
+            if ring_buffer.len > 0 {
+                events |= PollEvents::IN;
+            }
+            if ring_buffer.write_end_closed {
+                events |= PollEvents::HUP;
+            }
+        }
+        if self.writable {
+            if ring_buffer.read_end_closed {
+                events |= PollEvents::ERR;
+            } else if ring_buffer.available_write() > 0 {
+                events |= PollEvents::OUT;
+            }
+        }
+        events
+    }
+    fn stat(&self) -> Stat {
+        let mut stat = Stat::new(StatMode::FIFO);
+        stat.size = self.buffer.exclusive_access().len as u64;
+        stat
+    }
+}
+
+impl Drop for Pipe {
+    /// The last descriptor of this end is closed, wake the other side so it
+    /// can see EOF or `EPIPE`
+    fn drop(&mut self) {
+        let mut ring_buffer = self.buffer.exclusive_access();
+        let waiters = if self.readable {
+            ring_buffer.read_end_closed = true;
+            core::mem::take(&mut ring_buffer.write_waiters)
+        } else {
+            ring_buffer.write_end_closed = true;
+            core::mem::take(&mut ring_buffer.read_waiters)
+        };
+        drop(ring_buffer);
+        wakeup_all(waiters);
+    }
+}
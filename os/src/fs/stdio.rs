@@ -0,0 +1,109 @@
+//! Standard input and output over the SBI console
+use super::{File, PollEvents, Stat, StatMode};
+use crate::mm::UserSlice;
+use crate::sbi::{console_getchar, console_putchar};
+use crate::sync::UPSafeCell;
+use crate::syscall::{SysError, SysResult};
+use crate::task::suspend_current_and_run_next;
+
+/// stdin file, reads one char from the console at a time
+pub struct Stdin {
+    /// a char taken from the console by `poll` but not read yet
+    pending: UPSafeCell<Option<u8>>,
+}
+
+/// stdout (and stderr) file, writes raw bytes to the console
+pub struct Stdout;
+
+impl Stdin {
+    ///Create a `Stdin`
+    pub fn new() -> Self {
+        Self {
+            pending: unsafe { UPSafeCell::new(None) },
+        }
+    }
+    /// Take a char from the console without blocking
+    fn try_getchar(&self) -> Option<u8> {
+        let mut pending = self.pending.exclusive_access();
+        if pending.is_none() {
+            let c = console_getchar();
+            // 0 and usize::MAX (-1) both mean there is no input yet
+            if c != 0 && c != usize::MAX {
+                *pending = Some(c as u8);
+            }
+        }
+        pending.take()
+    }
+}
+
+impl Default for Stdin {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl File for Stdin {
+    fn readable(&self) -> bool {
+        true
+    }
+    fn writable(&self) -> bool {
+        false
+    }
+    fn read(&self, buf: UserSlice) -> SysResult<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        // check the buffer before a char is taken from the console
+        buf.buffers(true)?;
+        // only one char is read each time
+        let ch = loop {
+            if let Some(ch) = self.try_getchar() {
+                break ch;
+            }
+            suspend_current_and_run_next();
+        };
+        buf.copy_to_user(&[ch])
+    }
+    fn write(&self, _buf: UserSlice) -> SysResult<usize> {
+        Err(SysError::EBADF)
+    }
+    fn poll(&self) -> PollEvents {
+        if let Some(ch) = self.try_getchar() {
+            // keep the char for the next read
+            *self.pending.exclusive_access() = Some(ch);
+            PollEvents::IN
+        } else {
+            PollEvents::empty()
+        }
+    }
+    fn stat(&self) -> Stat {
+        Stat::new(StatMode::CHR)
+    }
+}
+
+impl File for Stdout {
+    fn readable(&self) -> bool {
+        false
+    }
+    fn writable(&self) -> bool {
+        true
+    }
+    fn read(&self, _buf: UserSlice) -> SysResult<usize> {
+        Err(SysError::EBADF)
+    }
+    fn write(&self, buf: UserSlice) -> SysResult<usize> {
+        // write raw bytes, so that a utf-8 char split across pages is still fine
+        for buffer in buf.buffers(false)? {
+            for &byte in buffer.iter() {
+                console_putchar(byte as usize);
+            }
+        }
+        Ok(buf.len())
+    }
+    fn poll(&self) -> PollEvents {
+        PollEvents::OUT
+    }
+    fn stat(&self) -> Stat {
+        Stat::new(StatMode::CHR)
+    }
+}
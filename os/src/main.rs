@@ -7,6 +7,8 @@
 //! - [`task`]: Task management
 //! - [`syscall`]: System call handling and implementation
 //! - [`mm`]: Address map using SV39
+//! - [`drivers`]: Device drivers, the virtio block device for now
+//! - [`fs`]: File abstraction and per-process file descriptors
 //! - [`sync`]:Wrap a static data structure inside it so that we are able to access it without any `unsafe`.
 //!
 //! The operating system also starts in this module. Kernel code starts
@@ -33,8 +35,9 @@ mod board;
 #[macro_use]
 mod console;
 mod config;
+mod drivers;
+pub mod fs;
 mod lang_items;
-mod loader;
 pub mod mm;
 mod sbi;
 pub mod sync;
@@ -46,7 +49,6 @@ pub mod trap;
 use core::arch::global_asm;
 
 global_asm!(include_str!("entry.asm"));
-global_asm!(include_str!("link_app.S"));
 /// clear BSS segment
 fn clear_bss() {
     extern "C" {
@@ -66,11 +68,12 @@ pub fn rust_main() -> ! {
     println!("[kernel] Hello, world!");
     mm::init();
     mm::remap_test();
+    drivers::init();
     trap::init();
     //trap::enable_interrupt();
     trap::enable_timer_interrupt();
     timer::set_next_trigger();
-    loader::list_apps();
+    fs::list_apps();
     task::add_initproc();
     task::run_tasks();
     panic!("Unreachable in rust_main!");
@@ -229,6 +229,10 @@ where
     pub fn get_end(&self) -> T {
         self.r
     }
+    /// whether `value` is in `[start, end)`
+    pub fn contains(&self, value: T) -> bool {
+        self.l <= value && value < self.r
+    }
 }
 impl<T> IntoIterator for SimpleRange<T>
 where
@@ -0,0 +1,110 @@
+//! Bitmap frame allocator
+use super::{FrameAllocator, FrameStats};
+use crate::mm::PhysPageNum;
+use alloc::vec;
+use alloc::vec::Vec;
+
+const BITS_PER_WORD: usize = u64::BITS as usize;
+
+/// One bit per frame, set if the frame is allocated. Single frames are
+/// searched for a word at a time from where the last one was found.
+pub struct BitmapFrameAllocator {
+    /// first frame managed
+    start: usize,
+    /// number of frames managed
+    len: usize,
+    bits: Vec<u64>,
+    free_frames: usize,
+    /// word where the search for a free frame starts
+    next_word: usize,
+}
+
+impl BitmapFrameAllocator {
+    fn is_used(&self, idx: usize) -> bool {
+        self.bits[idx / BITS_PER_WORD] & (1 << (idx % BITS_PER_WORD)) != 0
+    }
+    fn set_used(&mut self, idx: usize, used: bool) {
+        if used {
+            self.bits[idx / BITS_PER_WORD] |= 1 << (idx % BITS_PER_WORD);
+        } else {
+            self.bits[idx / BITS_PER_WORD] &= !(1 << (idx % BITS_PER_WORD));
+        }
+    }
+}
+
+impl FrameAllocator for BitmapFrameAllocator {
+    fn new() -> Self {
+        Self {
+            start: 0,
+            len: 0,
+            bits: Vec::new(),
+            free_frames: 0,
+            next_word: 0,
+        }
+    }
+    fn init(&mut self, l: PhysPageNum, r: PhysPageNum) {
+        self.start = l.0;
+        self.len = r.0 - l.0;
+        self.bits = vec![0; self.len.div_ceil(BITS_PER_WORD)];
+        // the bits past the end are never free
+        for idx in self.len..self.bits.len() * BITS_PER_WORD {
+            self.set_used(idx, true);
+        }
+        self.free_frames = self.len;
+    }
+    fn alloc(&mut self) -> Option<PhysPageNum> {
+        let words = self.bits.len();
+        let word = (0..words)
+            .map(|i| (self.next_word + i) % words)
+            .find(|&word| self.bits[word] != u64::MAX)?;
+        let idx = word * BITS_PER_WORD + self.bits[word].trailing_ones() as usize;
+        self.set_used(idx, true);
+        self.free_frames -= 1;
+        self.next_word = word;
+        Some(PhysPageNum(self.start + idx))
+    }
+    fn alloc_contiguous(&mut self, pages: usize, align: usize) -> Option<PhysPageNum> {
+        assert!(pages > 0 && align.is_power_of_two());
+        // the first index whose ppn is aligned
+        let mut idx = (self.start + align - 1) / align * align - self.start;
+        while idx + pages <= self.len {
+            match (idx..idx + pages).rev().find(|&i| self.is_used(i)) {
+                // skip past the last frame in the way
+                Some(used) => idx = (self.start + used + align) / align * align - self.start,
+                None => {
+                    for i in idx..idx + pages {
+                        self.set_used(i, true);
+                    }
+                    self.free_frames -= pages;
+                    return Some(PhysPageNum(self.start + idx));
+                }
+            }
+        }
+        None
+    }
+    fn dealloc(&mut self, ppn: PhysPageNum) {
+        let idx = ppn.0.wrapping_sub(self.start);
+        if idx >= self.len || !self.is_used(idx) {
+            panic!("Frame ppn={:#x} has not been allocated!", ppn.0);
+        }
+        self.set_used(idx, false);
+        self.free_frames += 1;
+    }
+    fn stats(&self) -> FrameStats {
+        let mut largest_free_block = 0;
+        let mut run = 0;
+        for idx in 0..self.len {
+            if self.is_used(idx) {
+                run = 0;
+            } else {
+                run += 1;
+                largest_free_block = largest_free_block.max(run);
+            }
+        }
+        FrameStats {
+            total_frames: self.len,
+            free_frames: self.free_frames,
+            largest_free_block,
+        }
+    }
+}
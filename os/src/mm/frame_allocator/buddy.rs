@@ -0,0 +1,114 @@
+//! Buddy system frame allocator
+use super::{FrameAllocator, FrameStats};
+use crate::mm::PhysPageNum;
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+
+/// Largest block, `1 << MAX_ORDER` frames
+const MAX_ORDER: usize = 20;
+
+/// Free frames are kept in blocks of `1 << order` frames, each starting at a
+/// ppn aligned to its size. A block is split to allocate from it, and merged
+/// with its buddy (the other half of the block twice as large) when both
+/// are free.
+pub struct BuddyFrameAllocator {
+    /// first frame managed
+    start: usize,
+    /// end of the frames managed
+    end: usize,
+    /// start of the free blocks, by order
+    free_lists: Vec<BTreeSet<usize>>,
+    free_frames: usize,
+}
+
+impl BuddyFrameAllocator {
+    /// Free `[start, end)`, cut into the largest aligned blocks
+    fn free_range(&mut self, mut start: usize, end: usize) {
+        while start < end {
+            let mut order = (start.trailing_zeros() as usize).min(MAX_ORDER);
+            while start + (1 << order) > end {
+                order -= 1;
+            }
+            self.free_block(start, order);
+            start += 1 << order;
+        }
+    }
+    /// Free a block and merge it with its buddies as far as possible
+    fn free_block(&mut self, mut start: usize, mut order: usize) {
+        let size = 1 << order;
+        let overlaps_free_block = (0..=MAX_ORDER).any(|o| {
+            if o >= order {
+                self.free_lists[o].contains(&(start & !((1 << o) - 1)))
+            } else {
+                self.free_lists[o]
+                    .range(start..start + size)
+                    .next()
+                    .is_some()
+            }
+        });
+        if start < self.start || start + size > self.end || overlaps_free_block {
+            panic!("Frame ppn={:#x} has not been allocated!", start);
+        }
+        self.free_frames += size;
+        while order < MAX_ORDER && self.free_lists[order].remove(&(start ^ (1 << order))) {
+            start &= !(1 << order);
+            order += 1;
+        }
+        self.free_lists[order].insert(start);
+    }
+    /// Allocate a block, splitting a larger one if needed
+    fn alloc_block(&mut self, order: usize) -> Option<usize> {
+        let mut current = (order..=MAX_ORDER).find(|&o| !self.free_lists[o].is_empty())?;
+        let start = self.free_lists[current].pop_first().unwrap();
+        // give back the upper halves
+        while current > order {
+            current -= 1;
+            self.free_lists[current].insert(start + (1 << current));
+        }
+        self.free_frames -= 1 << order;
+        Some(start)
+    }
+}
+
+impl FrameAllocator for BuddyFrameAllocator {
+    fn new() -> Self {
+        Self {
+            start: 0,
+            end: 0,
+            free_lists: (0..=MAX_ORDER).map(|_| BTreeSet::new()).collect(),
+            free_frames: 0,
+        }
+    }
+    fn init(&mut self, l: PhysPageNum, r: PhysPageNum) {
+        self.start = l.0;
+        self.end = r.0;
+        self.free_range(l.0, r.0);
+    }
+    fn alloc(&mut self) -> Option<PhysPageNum> {
+        self.alloc_block(0).map(PhysPageNum)
+    }
+    fn alloc_contiguous(&mut self, pages: usize, align: usize) -> Option<PhysPageNum> {
+        assert!(pages > 0 && align.is_power_of_two());
+        let order = (pages.next_power_of_two().max(align).trailing_zeros()) as usize;
+        if order > MAX_ORDER {
+            return None;
+        }
+        let start = self.alloc_block(order)?;
+        // the rest of the block is not needed
+        self.free_range(start + pages, start + (1 << order));
+        Some(PhysPageNum(start))
+    }
+    fn dealloc(&mut self, ppn: PhysPageNum) {
+        self.free_block(ppn.0, 0);
+    }
+    fn stats(&self) -> FrameStats {
+        FrameStats {
+            total_frames: self.end - self.start,
+            free_frames: self.free_frames,
+            largest_free_block: (0..=MAX_ORDER)
+                .rev()
+                .find(|&order| !self.free_lists[order].is_empty())
+                .map_or(0, |order| 1 << order),
+        }
+    }
+}
@@ -0,0 +1,185 @@
+//! Implementation of [`FrameAllocator`] which
+//! controls all the frames in the operating system.
+//!
+//! The buddy system is used by default, and a bitmap with the `frame-bitmap`
+//! feature. Both can allocate contiguous frames and free a frame in
+//! O(log n) at most.
+#[cfg_attr(not(feature = "frame-bitmap"), allow(dead_code))]
+mod bitmap;
+#[cfg_attr(feature = "frame-bitmap", allow(dead_code))]
+mod buddy;
+
+use super::{PhysAddr, PhysPageNum};
+use crate::config::MEMORY_END;
+use crate::sync::UPSafeCell;
+use alloc::vec::Vec;
+use core::fmt::{self, Debug, Formatter};
+use lazy_static::*;
+
+/// manage a frame which has the same lifecycle as the tracker
+pub struct FrameTracker {
+    ///
+    pub ppn: PhysPageNum,
+}
+
+impl FrameTracker {
+    ///Create an empty `FrameTracker`
+    pub fn new(ppn: PhysPageNum) -> Self {
+        // page cleaning
+        let bytes_array = ppn.get_bytes_array();
+        for i in bytes_array {
+            *i = 0;
+        }
+        Self { ppn }
+    }
+}
+
+impl Debug for FrameTracker {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!("FrameTracker:PPN={:#x}", self.ppn.0))
+    }
+}
+
+impl Drop for FrameTracker {
+    fn drop(&mut self) {
+        frame_dealloc(self.ppn);
+    }
+}
+
+/// Counters of a frame allocator
+#[derive(Copy, Clone, Debug)]
+pub struct FrameStats {
+    /// frames managed by the allocator
+    pub total_frames: usize,
+    /// frames not allocated
+    pub free_frames: usize,
+    /// the longest run of free frames that can be allocated at once
+    pub largest_free_block: usize,
+}
+
+#[allow(unused)]
+impl FrameStats {
+    /// frames allocated
+    pub fn used_frames(&self) -> usize {
+        self.total_frames - self.free_frames
+    }
+    /// Share of the free frames, in percent, that is not in the largest free
+    /// block. 0 if all free frames can be allocated at once.
+    pub fn fragmentation(&self) -> usize {
+        if self.free_frames == 0 {
+            0
+        } else {
+            (self.free_frames - self.largest_free_block) * 100 / self.free_frames
+        }
+    }
+}
+
+trait FrameAllocator {
+    fn new() -> Self;
+    /// Manage the frames in `[l, r)`
+    fn init(&mut self, l: PhysPageNum, r: PhysPageNum);
+    fn alloc(&mut self) -> Option<PhysPageNum>;
+    /// Allocate `pages` contiguous frames, the first one aligned to `align`
+    /// frames, which is a power of two
+    fn alloc_contiguous(&mut self, pages: usize, align: usize) -> Option<PhysPageNum>;
+    /// Free a frame, panics if it is not allocated
+    fn dealloc(&mut self, ppn: PhysPageNum);
+    fn stats(&self) -> FrameStats;
+}
+
+#[cfg(not(feature = "frame-bitmap"))]
+type FrameAllocatorImpl = buddy::BuddyFrameAllocator;
+#[cfg(feature = "frame-bitmap")]
+type FrameAllocatorImpl = bitmap::BitmapFrameAllocator;
+
+lazy_static! {
+    /// frame allocator instance through lazy_static!
+    pub static ref FRAME_ALLOCATOR: UPSafeCell<FrameAllocatorImpl> =
+        unsafe { UPSafeCell::new(FrameAllocatorImpl::new()) };
+}
+/// initiate the frame allocator using `ekernel` and `MEMORY_END`
+pub fn init_frame_allocator() {
+    extern "C" {
+        fn ekernel();
+    }
+    let mut frame_allocator = FRAME_ALLOCATOR.exclusive_access();
+    frame_allocator.init(
+        PhysAddr::from(ekernel as usize).ceil(),
+        PhysAddr::from(MEMORY_END).floor(),
+    );
+    println!(
+        "last {} Physical Frames.",
+        frame_allocator.stats().free_frames
+    );
+}
+/// allocate a frame
+pub fn frame_alloc() -> Option<FrameTracker> {
+    FRAME_ALLOCATOR
+        .exclusive_access()
+        .alloc()
+        .map(FrameTracker::new)
+}
+/// Allocate `pages` physically contiguous frames for DMA buffers or huge
+/// pages, the first one aligned to `align` frames, which must be a power of
+/// two. The frames are freed one by one as the trackers are dropped.
+pub fn frame_alloc_contiguous(pages: usize, align: usize) -> Option<Vec<FrameTracker>> {
+    let start = FRAME_ALLOCATOR
+        .exclusive_access()
+        .alloc_contiguous(pages, align)?;
+    Some(
+        (start.0..start.0 + pages)
+            .map(|ppn| FrameTracker::new(ppn.into()))
+            .collect(),
+    )
+}
+/// Allocate `pages` contiguous frames for a large allocation of the kernel
+/// heap, the first one aligned to `align` frames. Free them with
+/// [`frame_dealloc_large`]. Returns `None` as well if the frame allocator is
+/// in use.
+pub fn frame_alloc_large(pages: usize, align: usize) -> Option<PhysPageNum> {
+    FRAME_ALLOCATOR
+        .try_exclusive_access()?
+        .alloc_contiguous(pages, align)
+}
+/// Free the frames of [`frame_alloc_large`]
+pub fn frame_dealloc_large(start: PhysPageNum, pages: usize) {
+    let mut frame_allocator = FRAME_ALLOCATOR.exclusive_access();
+    for ppn in start.0..start.0 + pages {
+        frame_allocator.dealloc(ppn.into());
+    }
+}
+/// Allocate `pages` contiguous frames for the kernel heap, aligned to their
+/// number which must be a power of two. They are never freed. Returns `None`
+/// as well if the frame allocator is in use, as it allocates from the heap.
+pub fn frame_alloc_heap(pages: usize) -> Option<PhysPageNum> {
+    FRAME_ALLOCATOR
+        .try_exclusive_access()?
+        .alloc_contiguous(pages, pages)
+}
+/// deallocate a frame
+fn frame_dealloc(ppn: PhysPageNum) {
+    FRAME_ALLOCATOR.exclusive_access().dealloc(ppn);
+}
+/// Counters of the frame allocator
+pub fn frame_stats() -> FrameStats {
+    FRAME_ALLOCATOR.exclusive_access().stats()
+}
+
+#[allow(unused)]
+/// a simple test for frame allocator
+pub fn frame_allocator_test() {
+    let mut v: Vec<FrameTracker> = Vec::new();
+    for i in 0..5 {
+        let frame = frame_alloc().unwrap();
+        println!("{:?}", frame);
+        v.push(frame);
+    }
+    v.clear();
+    for i in 0..5 {
+        let frame = frame_alloc().unwrap();
+        println!("{:?}", frame);
+        v.push(frame);
+    }
+    drop(v);
+    println!("frame_allocator_test passed!");
+}
@@ -1,15 +1,151 @@
 //! The global allocator
-use crate::config::KERNEL_HEAP_SIZE;
+//!
+//! The heap starts in a static array and grows with frames taken from the
+//! frame allocator, which are never given back. It grows when an allocation
+//! fails, and ahead of time when little space is left: the frame allocator
+//! allocates from the heap itself, so it needs some room while the heap is
+//! growing.
+//!
+//! Large allocations, whose size is often chosen by user space, bypass the
+//! heap and take frames of their own, which are freed with the allocation.
+//! Otherwise a single large buffer would keep its frames in the heap forever.
+use super::frame_allocator::{frame_alloc_heap, frame_alloc_large, frame_dealloc_large};
+use super::{PhysAddr, PhysPageNum};
+use crate::config::{KERNEL_HEAP_SIZE, PAGE_SIZE};
 use buddy_system_allocator::LockedHeap;
+use core::alloc::{GlobalAlloc, Layout};
+use core::ptr::{null_mut, NonNull};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// Number of bytes the heap grows by at least
+const HEAP_GROW_SIZE: usize = 0x4_0000;
+/// The heap grows ahead of time when fewer bytes are free
+const HEAP_LOW_WATERMARK: usize = 0x2_0000;
+/// Allocations of this many bytes or more take frames of their own. The frame
+/// allocator never allocates that much from the heap, as it can not allocate
+/// frames for itself.
+const LARGE_ALLOC_SIZE: usize = 4 * PAGE_SIZE;
+
+/// buddy system of the heap
+static HEAP_ALLOCATOR: LockedHeap = LockedHeap::empty();
+/// frames taken from the frame allocator
+static GROWN_FRAMES: AtomicUsize = AtomicUsize::new(0);
+
+/// The allocator growing [`HEAP_ALLOCATOR`] when needed
+struct KernelHeap;
 
 #[global_allocator]
 /// heap allocator instance
-static HEAP_ALLOCATOR: LockedHeap = LockedHeap::empty();
+static KERNEL_HEAP: KernelHeap = KernelHeap;
+
+unsafe impl GlobalAlloc for KernelHeap {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if layout.size() >= LARGE_ALLOC_SIZE {
+            return alloc_large(layout);
+        }
+        loop {
+            let mut heap = HEAP_ALLOCATOR.lock();
+            if let Ok(ptr) = heap.alloc(layout) {
+                let low = heap.stats_total_bytes() - heap.stats_alloc_actual() < HEAP_LOW_WATERMARK;
+                drop(heap);
+                if low {
+                    grow(0);
+                }
+                return ptr.as_ptr();
+            }
+            // the frame allocator may use the heap while it grows
+            drop(heap);
+            // the buddy system needs a block of this size, aligned to it
+            let block = layout.size().next_power_of_two().max(layout.align());
+            if !grow(block) {
+                return null_mut();
+            }
+        }
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        if layout.size() >= LARGE_ALLOC_SIZE {
+            let start: PhysPageNum = PhysAddr::from(ptr as usize).into();
+            frame_dealloc_large(start, layout.size().div_ceil(PAGE_SIZE));
+            return;
+        }
+        HEAP_ALLOCATOR
+            .lock()
+            .dealloc(NonNull::new_unchecked(ptr), layout);
+    }
+}
+
+/// Allocate frames for a large allocation, null if there are not enough
+/// contiguous free frames
+fn alloc_large(layout: Layout) -> *mut u8 {
+    let pages = layout.size().div_ceil(PAGE_SIZE);
+    let align = layout.align().div_ceil(PAGE_SIZE).next_power_of_two();
+    match frame_alloc_large(pages, align) {
+        Some(ppn) => {
+            let start: PhysAddr = ppn.into();
+            let start: usize = start.into();
+            start as *mut u8
+        }
+        None => null_mut(),
+    }
+}
+
+/// Add a block of `bytes` bytes at least to the heap, aligned to its size.
+/// Returns false if there are not enough contiguous free frames, or if the
+/// frame allocator is the one allocating from the heap.
+fn grow(bytes: usize) -> bool {
+    let pages = bytes
+        .max(HEAP_GROW_SIZE)
+        .div_ceil(PAGE_SIZE)
+        .next_power_of_two();
+    let start: PhysAddr = match frame_alloc_heap(pages) {
+        Some(ppn) => ppn.into(),
+        None => return false,
+    };
+    let start: usize = start.into();
+    unsafe {
+        HEAP_ALLOCATOR
+            .lock()
+            .add_to_heap(start, start + pages * PAGE_SIZE);
+    }
+    GROWN_FRAMES.fetch_add(pages, Ordering::Relaxed);
+    true
+}
+
+/// Counters of the kernel heap
+#[allow(unused)]
+#[derive(Copy, Clone, Debug)]
+pub struct HeapStats {
+    /// bytes of the heap, the static array and the frames it grew by
+    pub total_bytes: usize,
+    /// bytes allocated, rounded up to the blocks of the buddy system
+    pub allocated_bytes: usize,
+    /// bytes asked for by the allocations
+    pub requested_bytes: usize,
+    /// frames taken from the frame allocator
+    pub grown_frames: usize,
+}
+
+/// Counters of the kernel heap
+pub fn heap_stats() -> HeapStats {
+    let heap = HEAP_ALLOCATOR.lock();
+    HeapStats {
+        total_bytes: heap.stats_total_bytes(),
+        allocated_bytes: heap.stats_alloc_actual(),
+        requested_bytes: heap.stats_alloc_user(),
+        grown_frames: GROWN_FRAMES.load(Ordering::Relaxed),
+    }
+}
 
 #[alloc_error_handler]
-/// panic when heap allocation error occurs
+/// panic when heap allocation error occurs, i.e. the heap can not grow. The
+/// allocations whose size is chosen by user space use `try_reserve` and fail
+/// with `ENOMEM` instead.
 pub fn handle_alloc_error(layout: core::alloc::Layout) -> ! {
-    panic!("Heap allocation error, layout = {:?}", layout);
+    panic!(
+        "Heap allocation error, layout = {:?}, {:?}",
+        layout,
+        heap_stats()
+    );
 }
 /// heap space ([u8; KERNEL_HEAP_SIZE])
 static mut HEAP_SPACE: [u8; KERNEL_HEAP_SIZE] = [0; KERNEL_HEAP_SIZE];
@@ -1,14 +1,19 @@
 //! Implementation of [`MapArea`] and [`MemorySet`].
+use super::page_replace::{PageReplacer, PageReplacerImpl};
+use super::swap::SwapSlot;
 use super::{frame_alloc, FrameTracker};
 use super::{PTEFlags, PageTable, PageTableEntry};
 use super::{PhysAddr, PhysPageNum, VirtAddr, VirtPageNum};
-use super::{StepByOne, VPNRange};
-use crate::config::{MEMORY_END, MMIO, PAGE_SIZE, TRAMPOLINE};
+use super::{SharedMemory, StepByOne, VPNRange};
+use crate::config::{MEMORY_END, MMIO, PAGE_SIZE, TRAMPOLINE, USER_STACK_BASE};
 use crate::sync::UPSafeCell;
+use crate::syscall::{SysError, SysResult};
+use crate::task::oom_kill;
 use alloc::collections::BTreeMap;
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 use core::arch::asm;
+use core::cmp::Ordering;
 use lazy_static::*;
 use riscv::register::satp;
 
@@ -28,37 +33,85 @@ extern "C" {
 lazy_static! {
     /// a memory set instance through lazy_static! managing kernel space
     pub static ref KERNEL_SPACE: Arc<UPSafeCell<MemorySet>> =
-        Arc::new(unsafe { UPSafeCell::new(MemorySet::new_kernel()) });
+        Arc::new(unsafe { UPSafeCell::new(MemorySet::new_kernel().unwrap()) });
 }
 /// memory set structure, controls virtual-memory space
 pub struct MemorySet {
     page_table: PageTable,
     areas: Vec<MapArea>,
+    /// start of the heap, right after the ELF image
+    heap_bottom: usize,
+    /// program break, the end of the heap
+    brk: usize,
+    /// user pages with a frame, which may be swapped out
+    replacer: PageReplacerImpl,
 }
 
 impl MemorySet {
     ///Create an empty `MemorySet`
-    pub fn new_bare() -> Self {
-        Self {
-            page_table: PageTable::new(),
+    pub fn new_bare() -> SysResult<Self> {
+        Ok(Self {
+            page_table: PageTable::new()?,
             areas: Vec::new(),
-        }
+            heap_bottom: 0,
+            brk: 0,
+            replacer: PageReplacerImpl::default(),
+        })
     }
     ///Get pagetable `root_ppn`
     pub fn token(&self) -> usize {
         self.page_table.token()
     }
-    /// Assume that no conflicts.
+    /// Assume that no conflicts. Fails with `ENOMEM` if there are not enough
+    /// free frames, nothing is mapped then.
     pub fn insert_framed_area(
         &mut self,
         start_va: VirtAddr,
         end_va: VirtAddr,
         permission: MapPermission,
-    ) {
+    ) -> SysResult<()> {
         self.push(
             MapArea::new(start_va, end_va, MapType::Framed, permission),
             None,
+        )
+    }
+    /// Frames are allocated on first touch, see [`MemorySet::handle_page_fault`].
+    /// Assume that no conflicts.
+    pub fn insert_lazy_area(
+        &mut self,
+        start_va: VirtAddr,
+        end_va: VirtAddr,
+        permission: MapPermission,
+    ) {
+        self.areas
+            .push(MapArea::new(start_va, end_va, MapType::Framed, permission));
+    }
+    /// Attach the first `pages` pages of `shared_memory` at `start_vpn`, the
+    /// frames are mapped at once. Assume that no conflicts.
+    pub fn attach_shared(
+        &mut self,
+        start_vpn: VirtPageNum,
+        pages: usize,
+        shared_memory: Arc<SharedMemory>,
+        permission: MapPermission,
+    ) -> SysResult<()> {
+        let mut map_area = MapArea::new(
+            start_vpn.into(),
+            VirtPageNum(start_vpn.0 + pages).into(),
+            MapType::Framed,
+            permission,
         );
+        let pte_flags = map_area.user_pte_flags().unwrap();
+        for (vpn, frame) in map_area.vpn_range.into_iter().zip(shared_memory.frames()) {
+            if let Err(err) = self.page_table.map(vpn, frame.ppn, pte_flags) {
+                map_area.unmap(&mut self.page_table);
+                return Err(err);
+            }
+            map_area.data_frames.insert(vpn, Arc::clone(frame));
+        }
+        map_area.shared_memory = Some(shared_memory);
+        self.areas.push(map_area);
+        Ok(())
     }
     ///Remove `MapArea` that starts with `start_vpn`
     pub fn remove_area_with_start_vpn(&mut self, start_vpn: VirtPageNum) {
@@ -72,26 +125,27 @@ impl MemorySet {
             self.areas.remove(idx);
         }
     }
-    fn push(&mut self, mut map_area: MapArea, data: Option<&[u8]>) {
-        map_area.map(&mut self.page_table);
+    fn push(&mut self, mut map_area: MapArea, data: Option<&[u8]>) -> SysResult<()> {
+        map_area.map(&mut self.page_table)?;
         if let Some(data) = data {
             map_area.copy_data(&self.page_table, data);
         }
         self.areas.push(map_area);
+        Ok(())
     }
     /// Mention that trampoline is not collected by areas.
-    fn map_trampoline(&mut self) {
+    fn map_trampoline(&mut self) -> SysResult<()> {
         self.page_table.map(
             VirtAddr::from(TRAMPOLINE).into(),
             PhysAddr::from(strampoline as usize).into(),
             PTEFlags::R | PTEFlags::X,
-        );
+        )
     }
     /// Without kernel stacks.
-    pub fn new_kernel() -> Self {
-        let mut memory_set = Self::new_bare();
+    pub fn new_kernel() -> SysResult<Self> {
+        let mut memory_set = Self::new_bare()?;
         // map trampoline
-        memory_set.map_trampoline();
+        memory_set.map_trampoline()?;
         // map kernel sections
         println!(".text [{:#x}, {:#x})", stext as usize, etext as usize);
         println!(".rodata [{:#x}, {:#x})", srodata as usize, erodata as usize);
@@ -109,7 +163,7 @@ impl MemorySet {
                 MapPermission::R | MapPermission::X,
             ),
             None,
-        );
+        )?;
         println!("mapping .rodata section");
         memory_set.push(
             MapArea::new(
@@ -119,7 +173,7 @@ impl MemorySet {
                 MapPermission::R,
             ),
             None,
-        );
+        )?;
         println!("mapping .data section");
         memory_set.push(
             MapArea::new(
@@ -129,7 +183,7 @@ impl MemorySet {
                 MapPermission::R | MapPermission::W,
             ),
             None,
-        );
+        )?;
         println!("mapping .bss section");
         memory_set.push(
             MapArea::new(
@@ -139,7 +193,7 @@ impl MemorySet {
                 MapPermission::R | MapPermission::W,
             ),
             None,
-        );
+        )?;
         println!("mapping physical memory");
         memory_set.push(
             MapArea::new(
@@ -149,7 +203,7 @@ impl MemorySet {
                 MapPermission::R | MapPermission::W,
             ),
             None,
-        );
+        )?;
         println!("mapping memory-mapped registers");
         for pair in MMIO {
             memory_set.push(
@@ -160,16 +214,16 @@ impl MemorySet {
                     MapPermission::R | MapPermission::W,
                 ),
                 None,
-            );
+            )?;
         }
-        memory_set
+        Ok(memory_set)
     }
     /// Include sections in elf and trampoline and TrapContext and user stack,
     /// also returns user_sp and entry point.
-    pub fn from_elf(elf_data: &[u8]) -> (Self, usize, usize) {
-        let mut memory_set = Self::new_bare();
+    pub fn from_elf(elf_data: &[u8]) -> SysResult<(Self, usize, usize)> {
+        let mut memory_set = Self::new_bare()?;
         // map trampoline
-        memory_set.map_trampoline();
+        memory_set.map_trampoline()?;
         // map program headers of elf, with U flag
         let elf = xmas_elf::ElfFile::new(elf_data).unwrap();
         let elf_header = elf.header;
@@ -193,36 +247,69 @@ impl MemorySet {
                 if ph_flags.is_execute() {
                     map_perm |= MapPermission::X;
                 }
-                let map_area = MapArea::new(start_va, end_va, MapType::Framed, map_perm);
+                let mut map_area = MapArea::new(start_va, end_va, MapType::Framed, map_perm);
                 max_end_vpn = map_area.vpn_range.get_end();
-                memory_set.push(
-                    map_area,
-                    Some(&elf.input[ph.offset() as usize..(ph.offset() + ph.file_size()) as usize]),
-                );
+                // pages are loaded on first touch
+                map_area.init_data = Some((
+                    map_area.vpn_range.get_start(),
+                    Arc::new(
+                        elf.input[ph.offset() as usize..(ph.offset() + ph.file_size()) as usize]
+                            .to_vec(),
+                    ),
+                ));
+                memory_set.areas.push(map_area);
             }
         }
-        // map user stack with U flags
+        // the heap is empty at first
         let max_end_va: VirtAddr = max_end_vpn.into();
-        let mut user_stack_base: usize = max_end_va.into();
+        memory_set.heap_bottom = max_end_va.into();
+        memory_set.brk = memory_set.heap_bottom;
+        memory_set.insert_lazy_area(
+            max_end_va,
+            max_end_va,
+            MapPermission::R | MapPermission::W | MapPermission::U,
+        );
+        // user stacks are mapped with the threads, below each there is a
         // guard page
-        user_stack_base += PAGE_SIZE;
-        // map TrapContext
-        (
+        Ok((
             memory_set,
-            user_stack_base,
+            USER_STACK_BASE,
             elf.header.pt2.entry_point() as usize,
-        )
+        ))
     }
-    ///Clone a same `MemorySet`
-    pub fn from_existed_user(user_space: &Self) -> Self {
-        let mut memory_set = Self::new_bare();
+    /// Clone a user `MemorySet` for fork.
+    ///
+    /// Frames of the areas accessible in U mode are shared copy-on-write: both
+    /// spaces map them read-only and the first write copies the page, see
+    /// [`MemorySet::handle_page_fault`]. Attached shared memory stays shared.
+    /// Pages not touched yet stay lazy, and pages swapped out share their
+    /// slots. Trap contexts are written by the kernel through their physical
+    /// pages, so they are copied at once.
+    ///
+    /// Fails with `ENOMEM` if there is no free frame for the page tables or
+    /// the trap contexts. Some pages of `user_space` may be read-only then,
+    /// they are made writable again on the next write.
+    pub fn from_existed_user(user_space: &mut Self) -> SysResult<Self> {
+        let mut memory_set = Self::new_bare()?;
+        memory_set.heap_bottom = user_space.heap_bottom;
+        memory_set.brk = user_space.brk;
+        memory_set.replacer = user_space.replacer.clone();
         // map trampoline
-        memory_set.map_trampoline();
-        // copy data sections/trap_context/user_stack
+        memory_set.map_trampoline()?;
         for area in user_space.areas.iter() {
-            let new_area = MapArea::from_another(area);
-            memory_set.push(new_area, None);
-            // copy data from another space
+            let mut new_area = MapArea::from_another(area);
+            if area.map_perm.contains(MapPermission::U) {
+                // data sections/user_stack
+                new_area.map_shared(
+                    area,
+                    &mut memory_set.page_table,
+                    &mut user_space.page_table,
+                )?;
+                memory_set.areas.push(new_area);
+                continue;
+            }
+            // trap_context
+            memory_set.push(new_area, None)?;
             for vpn in area.vpn_range {
                 let src_ppn = user_space.translate(vpn).unwrap().ppn();
                 let dst_ppn = memory_set.translate(vpn).unwrap().ppn();
@@ -231,7 +318,251 @@ impl MemorySet {
                     .copy_from_slice(src_ppn.get_bytes_array());
             }
         }
-        memory_set
+        // the parent lost its write permissions, its TLB is flushed by
+        // `__restore` when it goes back to U mode
+        Ok(memory_set)
+    }
+    /// Handle a page fault at `vpn` caused by an `access` (one of `R`, `W`
+    /// and `X`) in U mode. A page of a lazy area gets its frame, a page
+    /// swapped out is read back, and a page shared copy-on-write since fork
+    /// is copied on write.
+    ///
+    /// Fails with `EFAULT` on a segmentation fault, i.e. `vpn` is outside
+    /// every area or the area does not allow the access, and with `ENOMEM`
+    /// if no frame can be found, see [`MemorySet::alloc_user_frame`].
+    pub fn handle_page_fault(&mut self, vpn: VirtPageNum, access: MapPermission) -> SysResult<()> {
+        self.handle_page_fault_pinned(vpn, access, &[])
+    }
+    /// Same as [`MemorySet::handle_page_fault`], but the `pinned` pages are
+    /// never swapped out to make room, as the kernel is using their frames.
+    pub fn handle_page_fault_pinned(
+        &mut self,
+        vpn: VirtPageNum,
+        access: MapPermission,
+        pinned: &[VirtPageNum],
+    ) -> SysResult<()> {
+        let idx = self
+            .areas
+            .iter()
+            .position(|area| {
+                area.vpn_range.contains(vpn)
+                    && area.map_type == MapType::Framed
+                    && area.map_perm.contains(access | MapPermission::U)
+            })
+            .ok_or(SysError::EFAULT)?;
+        match self.page_table.translate(vpn) {
+            Some(pte) if pte.is_valid() => {
+                if !access.contains(MapPermission::W) || pte.writable() {
+                    // already mapped on behalf of a syscall, the stale TLB
+                    // entry is gone once back in U mode
+                    return Ok(());
+                }
+                let new_frame = if self.areas[idx].is_frame_shared(vpn) {
+                    Some(self.alloc_user_frame(pinned)?)
+                } else {
+                    None
+                };
+                self.areas[idx].copy_on_write(&mut self.page_table, vpn, new_frame);
+            }
+            _ => {
+                self.reserve_page_table(vpn, pinned)?;
+                let frame = self.alloc_user_frame(pinned)?;
+                self.areas[idx].map_lazy_one(&mut self.page_table, vpn, frame)?;
+                self.replacer.insert(vpn);
+            }
+        }
+        Ok(())
+    }
+    /// Allocate a frame for a user page, making room with
+    /// [`MemorySet::make_room`] while there is no free frame
+    fn alloc_user_frame(&mut self, pinned: &[VirtPageNum]) -> SysResult<FrameTracker> {
+        loop {
+            if let Some(frame) = frame_alloc() {
+                return Ok(frame);
+            }
+            self.make_room(pinned)?;
+        }
+    }
+    /// Create the page tables needed to map `vpn`, making room with
+    /// [`MemorySet::make_room`] while there is no free frame
+    fn reserve_page_table(&mut self, vpn: VirtPageNum, pinned: &[VirtPageNum]) -> SysResult<()> {
+        while !self.page_table.reserve(vpn) {
+            self.make_room(pinned)?;
+        }
+        Ok(())
+    }
+    /// Free some frame for the page fault being handled: swap out one of the
+    /// pages of this space, or else have the OOM killer kill the process
+    /// using the most memory. Fails with `ENOMEM` if that is the process of
+    /// this space, it is killed before going back to U mode.
+    fn make_room(&mut self, pinned: &[VirtPageNum]) -> SysResult<()> {
+        if self.swap_out_one(pinned) || oom_kill(self.resident_pages()) {
+            Ok(())
+        } else {
+            Err(SysError::ENOMEM)
+        }
+    }
+    /// Swap out a page picked by the replacement policy, returns false if
+    /// there is no swap slot or no page to swap out. Pages shared with
+    /// other spaces and `pinned` pages are never picked.
+    fn swap_out_one(&mut self, pinned: &[VirtPageNum]) -> bool {
+        let slot = match SwapSlot::alloc() {
+            Some(slot) => slot,
+            None => return false,
+        };
+        let areas = &self.areas;
+        let victim = self.replacer.pick(&mut self.page_table, |vpn| {
+            !pinned.contains(&vpn)
+                && areas
+                    .iter()
+                    .find(|area| area.vpn_range.contains(vpn))
+                    .map_or(false, |area| area.can_swap_out(vpn))
+        });
+        let vpn = match victim {
+            Some(vpn) => vpn,
+            None => return false,
+        };
+        self.areas
+            .iter_mut()
+            .find(|area| area.vpn_range.contains(vpn))
+            .unwrap()
+            .swap_out(&mut self.page_table, vpn, slot);
+        true
+    }
+    /// Whether any area overlaps `[start, end)`
+    pub fn overlaps(&self, start: VirtPageNum, end: VirtPageNum) -> bool {
+        self.areas
+            .iter()
+            .any(|area| area.vpn_range.get_start() < end && start < area.vpn_range.get_end())
+    }
+    /// Find `pages` free pages in `[start, end)`, returns the first one
+    pub fn find_free_range(
+        &self,
+        mut start: VirtPageNum,
+        end: VirtPageNum,
+        pages: usize,
+    ) -> Option<VirtPageNum> {
+        loop {
+            let range_end = VirtPageNum(start.0 + pages);
+            if range_end > end {
+                return None;
+            }
+            // skip past everything in the way and try again
+            match self
+                .areas
+                .iter()
+                .filter(|area| {
+                    area.vpn_range.get_start() < range_end && start < area.vpn_range.get_end()
+                })
+                .map(|area| area.vpn_range.get_end())
+                .max()
+            {
+                Some(area_end) => start = area_end,
+                None => return Some(start),
+            }
+        }
+    }
+    /// Split the user area containing `vpn` in two at `vpn`, if `vpn` is not
+    /// its start
+    fn split_user_area_at(&mut self, vpn: VirtPageNum) {
+        if let Some(idx) = self.areas.iter().position(|area| {
+            area.map_perm.contains(MapPermission::U)
+                && area.vpn_range.get_start() < vpn
+                && vpn < area.vpn_range.get_end()
+        }) {
+            let tail = self.areas[idx].split_off(vpn);
+            self.areas.push(tail);
+        }
+    }
+    /// Unmap every page of the user areas in `[start, end)`, splitting the
+    /// areas only partly inside it
+    pub fn remove_user_range(&mut self, start: VirtPageNum, end: VirtPageNum) {
+        self.split_user_area_at(start);
+        self.split_user_area_at(end);
+        let page_table = &mut self.page_table;
+        self.areas.retain_mut(|area| {
+            let inside = area.map_perm.contains(MapPermission::U)
+                && start <= area.vpn_range.get_start()
+                && area.vpn_range.get_end() <= end;
+            if inside {
+                area.unmap(page_table);
+            }
+            !inside
+        });
+        self.replacer.remove_range(start, end);
+    }
+    /// Move the program break by `increment` bytes and return the old one.
+    /// Returns `None` if the heap would shrink below its bottom or run into
+    /// another area or the thread stacks above `USER_STACK_BASE`.
+    pub fn sbrk(&mut self, increment: isize) -> Option<usize> {
+        let old_brk = self.brk;
+        let new_brk = old_brk
+            .checked_add_signed(increment)
+            .filter(|brk| (self.heap_bottom..=USER_STACK_BASE).contains(brk))?;
+        let heap_bottom = VirtAddr::from(self.heap_bottom).floor();
+        let old_end = VirtAddr::from(old_brk).ceil();
+        let new_end = VirtAddr::from(new_brk).ceil();
+        match new_end.cmp(&old_end) {
+            Ordering::Greater => {
+                if self.overlaps(old_end, new_end) {
+                    return None;
+                }
+                match self.areas.iter_mut().find(|area| {
+                    area.vpn_range.get_start() == heap_bottom && area.vpn_range.get_end() == old_end
+                }) {
+                    Some(area) => area.vpn_range = VPNRange::new(heap_bottom, new_end),
+                    // the heap was unmapped by munmap
+                    None => self.insert_lazy_area(
+                        old_end.into(),
+                        new_end.into(),
+                        MapPermission::R | MapPermission::W | MapPermission::U,
+                    ),
+                }
+            }
+            Ordering::Less => self.remove_user_range(new_end, old_end),
+            Ordering::Equal => {}
+        }
+        self.brk = new_brk;
+        Some(old_brk)
+    }
+    /// Change the permission of the user pages in `[start, end)`, splitting
+    /// the areas only partly inside it. Fails with `ENOMEM` if some page is
+    /// not mapped, and with `EACCES` if some page belongs to the kernel, like
+    /// the trap contexts.
+    pub fn mprotect(
+        &mut self,
+        start: VirtPageNum,
+        end: VirtPageNum,
+        permission: MapPermission,
+    ) -> SysResult<()> {
+        let mut covered = 0;
+        for area in self
+            .areas
+            .iter()
+            .filter(|area| area.vpn_range.get_start() < end && start < area.vpn_range.get_end())
+        {
+            if !area.map_perm.contains(MapPermission::U) {
+                return Err(SysError::EACCES);
+            }
+            covered +=
+                area.vpn_range.get_end().min(end).0 - area.vpn_range.get_start().max(start).0;
+        }
+        if covered != end.0 - start.0 {
+            return Err(SysError::ENOMEM);
+        }
+        self.split_user_area_at(start);
+        self.split_user_area_at(end);
+        // pages made accessible again may need new page tables
+        let result = self
+            .areas
+            .iter_mut()
+            .filter(|area| area.vpn_range.get_start() < end && start < area.vpn_range.get_end())
+            .try_for_each(|area| area.set_permission(&mut self.page_table, permission));
+        // drop the entries with the old permission
+        unsafe {
+            asm!("sfence.vma");
+        }
+        result
     }
     ///Refresh TLB with `sfence.vma`
     pub fn activate(&self) {
@@ -245,16 +576,65 @@ impl MemorySet {
     pub fn translate(&self, vpn: VirtPageNum) -> Option<PageTableEntry> {
         self.page_table.translate(vpn)
     }
-    ///Remove all `MapArea`
+    ///Remove all `MapArea`, frames still shared with other spaces are kept
+    ///until their last owner is gone
     pub fn recycle_data_pages(&mut self) {
         //*self = Self::new_bare();
         self.areas.clear();
+        self.replacer = PageReplacerImpl::default();
+    }
+    /// Remove the areas accessible in U mode, the trap contexts are kept
+    pub fn recycle_user_pages(&mut self) {
+        let page_table = &mut self.page_table;
+        self.areas.retain_mut(|area| {
+            let user = area.map_perm.contains(MapPermission::U);
+            if user {
+                area.unmap(page_table);
+            }
+            !user
+        });
+        self.replacer = PageReplacerImpl::default();
+    }
+    /// Number of user pages with a frame, frames shared with other spaces
+    /// included
+    pub fn resident_pages(&self) -> usize {
+        self.areas
+            .iter()
+            .filter(|area| area.map_perm.contains(MapPermission::U))
+            .map(|area| area.data_frames.len())
+            .sum()
+    }
+    /// Number of user pages swapped out
+    pub fn swapped_pages(&self) -> usize {
+        self.areas.iter().map(|area| area.swapped.len()).sum()
+    }
+    /// Number of frames holding the page tables, the root included
+    pub fn page_table_pages(&self) -> usize {
+        self.page_table.frames()
+    }
+    /// Number of pages with a frame in the area starting at `start_vpn`, 0 if
+    /// there is no such area
+    pub fn area_resident_pages(&self, start_vpn: VirtPageNum) -> usize {
+        self.areas
+            .iter()
+            .find(|area| area.vpn_range.get_start() == start_vpn)
+            .map_or(0, |area| area.data_frames.len())
     }
 }
 /// map area structure, controls a contiguous piece of virtual memory
 pub struct MapArea {
     vpn_range: VPNRange,
-    data_frames: BTreeMap<VirtPageNum, FrameTracker>,
+    /// frames may be shared with the areas of forked spaces, a page of a
+    /// lazy area has no frame until it is touched
+    data_frames: BTreeMap<VirtPageNum, Arc<FrameTracker>>,
+    /// the shared memory attached by this area, its frames are never copied
+    shared_memory: Option<Arc<SharedMemory>>,
+    /// pages swapped out, the slots may be shared with the areas of forked
+    /// spaces like frames
+    swapped: BTreeMap<VirtPageNum, Arc<SwapSlot>>,
+    /// initial content of a lazy area starting at the given page, loaded page
+    /// by page
+    init_data: Option<(VirtPageNum, Arc<Vec<u8>>)>,
     map_type: MapType,
     map_perm: MapPermission,
 }
@@ -271,6 +651,9 @@ impl MapArea {
         Self {
             vpn_range: VPNRange::new(start_vpn, end_vpn),
             data_frames: BTreeMap::new(),
+            shared_memory: None,
+            swapped: BTreeMap::new(),
+            init_data: None,
             map_type,
             map_perm,
         }
@@ -279,37 +662,213 @@ impl MapArea {
         Self {
             vpn_range: VPNRange::new(another.vpn_range.get_start(), another.vpn_range.get_end()),
             data_frames: BTreeMap::new(),
+            shared_memory: another.shared_memory.clone(),
+            swapped: another.swapped.clone(),
+            init_data: another.init_data.clone(),
             map_type: another.map_type,
             map_perm: another.map_perm,
         }
     }
-    pub fn map_one(&mut self, page_table: &mut PageTable, vpn: VirtPageNum) {
-        let ppn: PhysPageNum;
+    pub fn map_one(&mut self, page_table: &mut PageTable, vpn: VirtPageNum) -> SysResult<()> {
+        let pte_flags = PTEFlags::from_bits(self.map_perm.bits).unwrap();
         match self.map_type {
-            MapType::Identical => {
-                ppn = PhysPageNum(vpn.0);
-            }
+            MapType::Identical => page_table.map(vpn, PhysPageNum(vpn.0), pte_flags),
             MapType::Framed => {
-                let frame = frame_alloc().unwrap();
-                ppn = frame.ppn;
-                self.data_frames.insert(vpn, frame);
+                let frame = frame_alloc().ok_or(SysError::ENOMEM)?;
+                page_table.map(vpn, frame.ppn, pte_flags)?;
+                self.data_frames.insert(vpn, Arc::new(frame));
+                Ok(())
             }
         }
+    }
+    /// Map the frames of `another` into `page_table` as well, both read-only
+    /// unless they are shared memory
+    pub fn map_shared(
+        &mut self,
+        another: &Self,
+        page_table: &mut PageTable,
+        another_page_table: &mut PageTable,
+    ) -> SysResult<()> {
+        let copy_on_write = self.shared_memory.is_none();
+        let pte_flags = self.user_pte_flags().map(|flags| {
+            if copy_on_write {
+                flags - PTEFlags::W
+            } else {
+                flags
+            }
+        });
+        for (vpn, frame) in another.data_frames.iter() {
+            if let Some(pte_flags) = pte_flags {
+                page_table.map(*vpn, frame.ppn, pte_flags)?;
+                if copy_on_write {
+                    another_page_table.remap(*vpn, frame.ppn, pte_flags);
+                }
+            }
+            self.data_frames.insert(*vpn, Arc::clone(frame));
+        }
+        Ok(())
+    }
+    /// PTE flags of the pages of a user area, `None` if they can not be
+    /// accessed at all. Such pages are kept out of the page table, as a PTE
+    /// without `R`, `W` and `X` would point to the next level.
+    fn user_pte_flags(&self) -> Option<PTEFlags> {
+        if self
+            .map_perm
+            .intersects(MapPermission::R | MapPermission::W | MapPermission::X)
+        {
+            Some(PTEFlags::from_bits(self.map_perm.bits).unwrap())
+        } else {
+            None
+        }
+    }
+    /// Change the permission of the pages, a frame still shared since fork
+    /// stays read-only until it is copied, unless it is shared memory
+    pub fn set_permission(
+        &mut self,
+        page_table: &mut PageTable,
+        permission: MapPermission,
+    ) -> SysResult<()> {
+        self.map_perm = permission;
+        let pte_flags = self.user_pte_flags();
+        for (vpn, frame) in self.data_frames.iter() {
+            let mapped = page_table
+                .translate(*vpn)
+                .map_or(false, |pte| pte.is_valid());
+            match pte_flags {
+                Some(mut pte_flags) => {
+                    if self.shared_memory.is_none() && Arc::strong_count(frame) > 1 {
+                        pte_flags.remove(PTEFlags::W);
+                    }
+                    if mapped {
+                        page_table.remap(*vpn, frame.ppn, pte_flags);
+                    } else {
+                        page_table.map(*vpn, frame.ppn, pte_flags)?;
+                    }
+                }
+                None if mapped => page_table.unmap(*vpn),
+                None => {}
+            }
+        }
+        Ok(())
+    }
+    /// Whether the frame of `vpn` is still shared with other spaces
+    pub fn is_frame_shared(&self, vpn: VirtPageNum) -> bool {
+        Arc::strong_count(&self.data_frames[&vpn]) > 1
+    }
+    /// Make `vpn` writable again, copying its frame to `new_frame` first if
+    /// it is still shared, see [`MapArea::is_frame_shared`]
+    pub fn copy_on_write(
+        &mut self,
+        page_table: &mut PageTable,
+        vpn: VirtPageNum,
+        new_frame: Option<FrameTracker>,
+    ) {
+        let frame = self.data_frames.get_mut(&vpn).unwrap();
+        if Arc::strong_count(frame) > 1 {
+            let new_frame = new_frame.unwrap();
+            new_frame
+                .ppn
+                .get_bytes_array()
+                .copy_from_slice(frame.ppn.get_bytes_array());
+            *frame = Arc::new(new_frame);
+        }
         let pte_flags = PTEFlags::from_bits(self.map_perm.bits).unwrap();
-        page_table.map(vpn, ppn, pte_flags);
+        page_table.remap(vpn, frame.ppn, pte_flags);
+    }
+    /// Whether `vpn` has a frame used by this area only, which can be
+    /// swapped out
+    pub fn can_swap_out(&self, vpn: VirtPageNum) -> bool {
+        self.shared_memory.is_none()
+            && self
+                .data_frames
+                .get(&vpn)
+                .map_or(false, |frame| Arc::strong_count(frame) == 1)
+    }
+    /// Write the frame of `vpn` to `slot` and free it, see
+    /// [`MapArea::can_swap_out`]
+    pub fn swap_out(&mut self, page_table: &mut PageTable, vpn: VirtPageNum, slot: SwapSlot) {
+        let frame = self.data_frames.remove(&vpn).unwrap();
+        slot.write(&frame);
+        if page_table
+            .translate(vpn)
+            .map_or(false, |pte| pte.is_valid())
+        {
+            page_table.unmap(vpn);
+        }
+        self.swapped.insert(vpn, Arc::new(slot));
+    }
+    /// Give `vpn` its `frame` on first touch and fill it from the initial
+    /// data of the area, or from the swap slot if it was swapped out
+    pub fn map_lazy_one(
+        &mut self,
+        page_table: &mut PageTable,
+        vpn: VirtPageNum,
+        frame: FrameTracker,
+    ) -> SysResult<()> {
+        let pte_flags = PTEFlags::from_bits(self.map_perm.bits).unwrap();
+        page_table.map(vpn, frame.ppn, pte_flags)?;
+        if let Some(slot) = self.swapped.remove(&vpn) {
+            // the slot is freed unless a forked space still needs it
+            slot.read(&frame);
+        } else if let Some((data_vpn, data)) = &self.init_data {
+            // data: start-aligned but maybe with shorter length
+            let start = (vpn.0 - data_vpn.0) * PAGE_SIZE;
+            if start < data.len() {
+                let src = &data[start..data.len().min(start + PAGE_SIZE)];
+                frame.ppn.get_bytes_array()[..src.len()].copy_from_slice(src);
+            }
+        }
+        self.data_frames.insert(vpn, Arc::new(frame));
+        Ok(())
+    }
+    /// Split off `[at, end)` into a new area, the frames there go with it
+    pub fn split_off(&mut self, at: VirtPageNum) -> Self {
+        let tail = Self {
+            vpn_range: VPNRange::new(at, self.vpn_range.get_end()),
+            data_frames: self.data_frames.split_off(&at),
+            shared_memory: self.shared_memory.clone(),
+            swapped: self.swapped.split_off(&at),
+            init_data: self.init_data.clone(),
+            map_type: self.map_type,
+            map_perm: self.map_perm,
+        };
+        self.vpn_range = VPNRange::new(self.vpn_range.get_start(), at);
+        tail
     }
     pub fn unmap_one(&mut self, page_table: &mut PageTable, vpn: VirtPageNum) {
-        if self.map_type == MapType::Framed {
-            self.data_frames.remove(&vpn);
+        self.swapped.remove(&vpn);
+        if self.map_type == MapType::Framed
+            && (self.data_frames.remove(&vpn).is_none() || self.user_pte_flags().is_none())
+        {
+            // never touched, or not in the page table at all
+            return;
         }
         page_table.unmap(vpn);
     }
-    pub fn map(&mut self, page_table: &mut PageTable) {
+    /// Map every page, fails with `ENOMEM` if there are not enough free
+    /// frames and the pages mapped so far are unmapped again
+    pub fn map(&mut self, page_table: &mut PageTable) -> SysResult<()> {
         for vpn in self.vpn_range {
-            self.map_one(page_table, vpn);
+            if let Err(err) = self.map_one(page_table, vpn) {
+                for mapped in VPNRange::new(self.vpn_range.get_start(), vpn) {
+                    self.unmap_one(page_table, mapped);
+                }
+                return Err(err);
+            }
         }
+        Ok(())
     }
     pub fn unmap(&mut self, page_table: &mut PageTable) {
+        if self.map_type == MapType::Framed {
+            // only the pages with a frame are in the page table, a lazy area
+            // may be far larger
+            self.swapped.clear();
+            let vpns: Vec<VirtPageNum> = self.data_frames.keys().copied().collect();
+            for vpn in vpns {
+                self.unmap_one(page_table, vpn);
+            }
+            return;
+        }
         for vpn in self.vpn_range {
             self.unmap_one(page_table, vpn);
         }
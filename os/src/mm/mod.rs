@@ -9,15 +9,76 @@ mod address;
 mod frame_allocator;
 mod heap_allocator;
 mod memory_set;
+mod page_replace;
 mod page_table;
+mod shared_memory;
+mod swap;
+mod user_ptr;
 
 pub use address::{PhysAddr, PhysPageNum, VirtAddr, VirtPageNum};
 use address::{StepByOne, VPNRange};
-pub use frame_allocator::{frame_alloc, FrameTracker};
+pub use frame_allocator::{frame_alloc, frame_alloc_contiguous, FrameTracker};
 pub use memory_set::remap_test;
 pub use memory_set::{MapPermission, MemorySet, KERNEL_SPACE};
-pub use page_table::{translated_byte_buffer, translated_refmut, translated_str, PageTableEntry};
+pub use page_table::PageTableEntry;
+pub use shared_memory::SharedMemory;
 use page_table::{PTEFlags, PageTable};
+pub use user_ptr::{UserCStr, UserPtr, UserSlice};
+
+/// Memory usage returned by `mem_stat`, all counts are in pages
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct MemStat {
+    /// frames managed by the frame allocator
+    pub total_frames: usize,
+    /// frames not allocated
+    pub free_frames: usize,
+    /// frames the kernel heap grew by, they are never freed
+    pub heap_frames: usize,
+    /// bytes allocated from the kernel heap
+    pub heap_allocated_bytes: usize,
+    /// slots of the swap device in use
+    pub swap_used_slots: usize,
+    /// pages read back from the swap device since boot
+    pub swap_page_ins: usize,
+    /// pages written to the swap device since boot
+    pub swap_page_outs: usize,
+    /// user pages of the process with a frame, shared frames included
+    pub resident_pages: usize,
+    /// user pages of the process swapped out
+    pub swapped_pages: usize,
+    /// frames holding the page tables of the process
+    pub page_table_pages: usize,
+    /// pages of the user stacks of all threads of the process with a frame
+    pub stack_pages: usize,
+}
+
+impl MemStat {
+    /// Usage of `memory_set`, with the user stacks of its threads starting at
+    /// `ustack_bottoms`, and of the whole system
+    pub fn new(memory_set: &MemorySet, ustack_bottoms: &[VirtPageNum]) -> Self {
+        let frames = frame_allocator::frame_stats();
+        let heap = heap_allocator::heap_stats();
+        let swap = swap::swap_stats();
+        Self {
+            total_frames: frames.total_frames,
+            free_frames: frames.free_frames,
+            heap_frames: heap.grown_frames,
+            heap_allocated_bytes: heap.allocated_bytes,
+            swap_used_slots: swap.used_slots,
+            swap_page_ins: swap.page_ins,
+            swap_page_outs: swap.page_outs,
+            resident_pages: memory_set.resident_pages(),
+            swapped_pages: memory_set.swapped_pages(),
+            page_table_pages: memory_set.page_table_pages(),
+            stack_pages: ustack_bottoms
+                .iter()
+                .map(|&ustack_bottom| memory_set.area_resident_pages(ustack_bottom))
+                .sum(),
+        }
+    }
+}
+
 /// initiate heap allocator, frame allocator and kernel space
 pub fn init() {
     heap_allocator::init_heap();
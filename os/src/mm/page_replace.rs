@@ -0,0 +1,143 @@
+//! Page replacement policies, which pick the page to swap out
+//!
+//! Every user space keeps the pages that have a frame in a [`PageReplacer`],
+//! and the one used is chosen when building the kernel: clock by default,
+//! FIFO with the `swap-fifo` feature and aging with the `swap-lru` feature.
+use super::{PageTable, VirtPageNum};
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+
+#[cfg(all(feature = "swap-fifo", feature = "swap-lru"))]
+compile_error!("the features swap-fifo and swap-lru can not be enabled together");
+
+#[cfg(feature = "swap-fifo")]
+/// the page replacement policy chosen when building the kernel
+pub type PageReplacerImpl = FifoReplacer;
+#[cfg(feature = "swap-lru")]
+/// the page replacement policy chosen when building the kernel
+pub type PageReplacerImpl = LruReplacer;
+#[cfg(not(any(feature = "swap-fifo", feature = "swap-lru")))]
+/// the page replacement policy chosen when building the kernel
+pub type PageReplacerImpl = ClockReplacer;
+
+/// Tracks the pages of a space that have a frame
+pub trait PageReplacer: Clone + Default {
+    /// `vpn` got a frame
+    fn insert(&mut self, vpn: VirtPageNum);
+    /// The pages in `[start, end)` are gone
+    fn remove_range(&mut self, start: VirtPageNum, end: VirtPageNum);
+    /// Pick a page to swap out among those `evictable` accepts, and forget
+    /// it. The `A` bits of the pages are read and cleared in `page_table`.
+    fn pick(
+        &mut self,
+        page_table: &mut PageTable,
+        evictable: impl FnMut(VirtPageNum) -> bool,
+    ) -> Option<VirtPageNum>;
+}
+
+/// First in, first out
+#[cfg_attr(not(feature = "swap-fifo"), allow(dead_code))]
+#[derive(Clone, Default)]
+pub struct FifoReplacer {
+    queue: VecDeque<VirtPageNum>,
+}
+
+impl PageReplacer for FifoReplacer {
+    fn insert(&mut self, vpn: VirtPageNum) {
+        self.queue.push_back(vpn);
+    }
+    fn remove_range(&mut self, start: VirtPageNum, end: VirtPageNum) {
+        self.queue.retain(|vpn| *vpn < start || end <= *vpn);
+    }
+    fn pick(
+        &mut self,
+        _page_table: &mut PageTable,
+        mut evictable: impl FnMut(VirtPageNum) -> bool,
+    ) -> Option<VirtPageNum> {
+        let idx = self.queue.iter().position(|vpn| evictable(*vpn))?;
+        self.queue.remove(idx)
+    }
+}
+
+/// Second chance: a page accessed since the hand passed it last time is
+/// skipped once
+#[cfg_attr(any(feature = "swap-fifo", feature = "swap-lru"), allow(dead_code))]
+#[derive(Clone, Default)]
+pub struct ClockReplacer {
+    /// the hand points to the front
+    ring: VecDeque<VirtPageNum>,
+}
+
+impl PageReplacer for ClockReplacer {
+    fn insert(&mut self, vpn: VirtPageNum) {
+        // just behind the hand
+        self.ring.push_back(vpn);
+    }
+    fn remove_range(&mut self, start: VirtPageNum, end: VirtPageNum) {
+        self.ring.retain(|vpn| *vpn < start || end <= *vpn);
+    }
+    fn pick(
+        &mut self,
+        page_table: &mut PageTable,
+        mut evictable: impl FnMut(VirtPageNum) -> bool,
+    ) -> Option<VirtPageNum> {
+        // the second round finds the pages whose A bit was cleared in the first
+        for _ in 0..2 * self.ring.len() {
+            let vpn = self.ring.pop_front().unwrap();
+            if evictable(vpn) && !page_table.take_accessed(vpn) {
+                return Some(vpn);
+            }
+            self.ring.push_back(vpn);
+        }
+        None
+    }
+}
+
+/// Number of pages aged each time a page is picked by [`LruReplacer`]
+const LRU_SCAN_PAGES: usize = 32;
+
+/// LRU approximated by aging: the `A` bit is shifted into an 8-bit history
+/// of each page, and the page with the smallest history is picked. Only the
+/// next [`LRU_SCAN_PAGES`] pages are aged and compared each time, they move
+/// to the back afterwards.
+#[cfg_attr(not(feature = "swap-lru"), allow(dead_code))]
+#[derive(Clone, Default)]
+pub struct LruReplacer {
+    /// pages with their history
+    queue: VecDeque<(VirtPageNum, u8)>,
+}
+
+impl PageReplacer for LruReplacer {
+    fn insert(&mut self, vpn: VirtPageNum) {
+        // just used
+        self.queue.push_back((vpn, 1 << 7));
+    }
+    fn remove_range(&mut self, start: VirtPageNum, end: VirtPageNum) {
+        self.queue.retain(|(vpn, _)| *vpn < start || end <= *vpn);
+    }
+    fn pick(
+        &mut self,
+        page_table: &mut PageTable,
+        mut evictable: impl FnMut(VirtPageNum) -> bool,
+    ) -> Option<VirtPageNum> {
+        let mut victim: Option<VirtPageNum> = None;
+        let mut victim_age = u8::MAX;
+        let mut scanned = 0;
+        let mut aged: Vec<(VirtPageNum, u8)> = Vec::new();
+        // stop early only when there is a candidate already
+        while scanned < self.queue.len() && (scanned < LRU_SCAN_PAGES || victim.is_none()) {
+            let (vpn, mut age) = self.queue[scanned];
+            age = age >> 1 | (page_table.take_accessed(vpn) as u8) << 7;
+            if evictable(vpn) && (victim.is_none() || age < victim_age) {
+                victim = Some(vpn);
+                victim_age = age;
+            }
+            aged.push((vpn, age));
+            scanned += 1;
+        }
+        self.queue.drain(..scanned);
+        self.queue
+            .extend(aged.into_iter().filter(|(vpn, _)| Some(*vpn) != victim));
+        victim
+    }
+}
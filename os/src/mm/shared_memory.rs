@@ -0,0 +1,51 @@
+//! Shared memory, a set of frames attached into several memory sets
+use super::{frame_alloc, FrameTracker};
+use crate::sync::UPSafeCell;
+use alloc::collections::BTreeMap;
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use lazy_static::*;
+
+/// Zeroed frames shared by every area attaching them, freed when the last
+/// attachment is gone
+pub struct SharedMemory {
+    frames: Vec<Arc<FrameTracker>>,
+}
+
+lazy_static! {
+    /// Named shared memory, alive while attached somewhere
+    static ref NAMED_SHARED_MEMORY: UPSafeCell<BTreeMap<usize, Weak<SharedMemory>>> =
+        unsafe { UPSafeCell::new(BTreeMap::new()) };
+}
+
+impl SharedMemory {
+    /// New shared memory of `pages` pages, `None` if there are not enough
+    /// free frames
+    pub fn new(pages: usize) -> Option<Arc<Self>> {
+        let frames = (0..pages)
+            .map(|_| frame_alloc().map(Arc::new))
+            .collect::<Option<Vec<_>>>()?;
+        Some(Arc::new(Self { frames }))
+    }
+    /// The shared memory named `key`, created with `pages` pages if it is not
+    /// attached anywhere. Returns `None` if there are not enough free frames.
+    pub fn get_or_create(key: usize, pages: usize) -> Option<Arc<Self>> {
+        let mut named = NAMED_SHARED_MEMORY.exclusive_access();
+        if let Some(shared_memory) = named.get(&key).and_then(Weak::upgrade) {
+            return Some(shared_memory);
+        }
+        let shared_memory = Self::new(pages)?;
+        named.insert(key, Arc::downgrade(&shared_memory));
+        // forget the names nobody uses any more
+        named.retain(|_, shared_memory| shared_memory.strong_count() > 0);
+        Some(shared_memory)
+    }
+    /// Number of pages
+    pub fn pages(&self) -> usize {
+        self.frames.len()
+    }
+    /// The frames, one per page
+    pub fn frames(&self) -> &[Arc<FrameTracker>] {
+        &self.frames
+    }
+}
@@ -0,0 +1,116 @@
+//! Swap space on [`SWAP_DEVICE`]
+//!
+//! The device is cut into slots of one page each. A page swapped out owns a
+//! [`SwapSlot`], which goes back to the swap space when dropped. Slots of
+//! pages swapped out before fork are shared by both spaces like frames are,
+//! and the first one to swap the page in reads its own copy.
+use super::FrameTracker;
+use crate::config::PAGE_SIZE;
+use crate::drivers::block::{SECTOR_SIZE, SWAP_DEVICE};
+use crate::sync::UPSafeCell;
+use alloc::vec::Vec;
+use lazy_static::*;
+
+/// Number of blocks of the swap device holding a page
+const BLOCKS_PER_SLOT: usize = PAGE_SIZE / SECTOR_SIZE;
+
+/// Counters of the swap space
+#[allow(unused)]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct SwapStats {
+    /// pages read back from the swap device
+    pub page_ins: usize,
+    /// pages written to the swap device
+    pub page_outs: usize,
+    /// slots in use
+    pub used_slots: usize,
+    /// slots of the swap device
+    pub total_slots: usize,
+}
+
+/// Allocator of the slots, recycled slots are used first
+struct SwapSpace {
+    current: usize,
+    end: usize,
+    recycled: Vec<usize>,
+    stats: SwapStats,
+}
+
+impl SwapSpace {
+    fn new() -> Self {
+        let end = SWAP_DEVICE
+            .as_ref()
+            .map_or(0, |device| device.num_blocks() / BLOCKS_PER_SLOT);
+        Self {
+            current: 0,
+            end,
+            recycled: Vec::new(),
+            stats: SwapStats {
+                total_slots: end,
+                ..Default::default()
+            },
+        }
+    }
+    fn alloc(&mut self) -> Option<usize> {
+        let slot = if let Some(slot) = self.recycled.pop() {
+            slot
+        } else if self.current == self.end {
+            return None;
+        } else {
+            self.current += 1;
+            self.current - 1
+        };
+        self.stats.used_slots += 1;
+        Some(slot)
+    }
+    fn dealloc(&mut self, slot: usize) {
+        self.stats.used_slots -= 1;
+        self.recycled.push(slot);
+    }
+}
+
+lazy_static! {
+    static ref SWAP_SPACE: UPSafeCell<SwapSpace> = unsafe { UPSafeCell::new(SwapSpace::new()) };
+}
+
+/// A slot of the swap device holding a page
+pub struct SwapSlot(usize);
+
+impl SwapSlot {
+    /// Take a free slot, `None` if there is no swap device or it is full
+    pub fn alloc() -> Option<Self> {
+        SWAP_SPACE.exclusive_access().alloc().map(Self)
+    }
+    /// Write the page in `frame` to the slot
+    pub fn write(&self, frame: &FrameTracker) {
+        let device = SWAP_DEVICE.as_ref().unwrap();
+        for (i, block) in frame.ppn.get_bytes_array().chunks(SECTOR_SIZE).enumerate() {
+            device.write_block(self.0 * BLOCKS_PER_SLOT + i, block);
+        }
+        SWAP_SPACE.exclusive_access().stats.page_outs += 1;
+    }
+    /// Read the page in the slot to `frame`
+    pub fn read(&self, frame: &FrameTracker) {
+        let device = SWAP_DEVICE.as_ref().unwrap();
+        for (i, block) in frame
+            .ppn
+            .get_bytes_array()
+            .chunks_mut(SECTOR_SIZE)
+            .enumerate()
+        {
+            device.read_block(self.0 * BLOCKS_PER_SLOT + i, block);
+        }
+        SWAP_SPACE.exclusive_access().stats.page_ins += 1;
+    }
+}
+
+impl Drop for SwapSlot {
+    fn drop(&mut self) {
+        SWAP_SPACE.exclusive_access().dealloc(self.0);
+    }
+}
+
+/// Counters of the swap space
+pub fn swap_stats() -> SwapStats {
+    SWAP_SPACE.exclusive_access().stats
+}
@@ -0,0 +1,228 @@
+//! Fault-safe access to user space memory.
+//!
+//! Every page touched on behalf of a syscall is looked up in the user page
+//! table and checked for the `V`, `U` and `R`/`W` bits before the kernel
+//! reads or writes it, so a bad pointer from user space ends up as `EFAULT`
+//! instead of a kernel panic. A lazy page is mapped and a page shared
+//! copy-on-write since fork is copied before the kernel touches it, just as
+//! if the process had touched it itself. The pages of a buffer already
+//! translated are pinned, so they are not swapped out to make room for the
+//! next ones.
+use super::{MapPermission, PageTable, PhysPageNum, StepByOne, VirtAddr, VirtPageNum};
+use crate::config::{PAGE_SIZE, USER_SPACE_END};
+use crate::syscall::{SysError, SysResult};
+use crate::task::current_user_process;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+use core::mem::{size_of, MaybeUninit};
+
+/// Max length (including the terminating `\0`) of a string read by [`UserCStr`]
+pub const USER_CSTR_MAX: usize = PAGE_SIZE;
+
+/// Handle the page fault the current process would get on `vpn`
+fn handle_page_fault(
+    token: usize,
+    vpn: VirtPageNum,
+    access: MapPermission,
+    pinned: &[VirtPageNum],
+) -> SysResult<()> {
+    let process = current_user_process();
+    let mut inner = process.inner_exclusive_access();
+    if inner.memory_set.token() != token {
+        return Err(SysError::EFAULT);
+    }
+    inner
+        .memory_set
+        .handle_page_fault_pinned(vpn, access, pinned)
+}
+
+/// Translate a user page, checking that it is mapped, accessible in U mode and
+/// readable (or writable if `write` is set). The `pinned` pages stay where
+/// they are if a page fault has to be handled.
+fn translate_user_page(
+    page_table: &PageTable,
+    va: usize,
+    write: bool,
+    pinned: &[VirtPageNum],
+) -> SysResult<PhysPageNum> {
+    if va >= USER_SPACE_END {
+        return Err(SysError::EFAULT);
+    }
+    let vpn = VirtAddr::from(va).floor();
+    let pte = match page_table.translate(vpn) {
+        Some(pte) if pte.is_valid() && (!write || pte.writable()) => pte,
+        _ => {
+            let access = if write {
+                MapPermission::W
+            } else {
+                MapPermission::R
+            };
+            handle_page_fault(page_table.token(), vpn, access, pinned)?;
+            page_table.translate(vpn).unwrap()
+        }
+    };
+    if !pte.is_valid() || !pte.is_user() || !pte.readable() || (write && !pte.writable()) {
+        return Err(SysError::EFAULT);
+    }
+    Ok(pte.ppn())
+}
+
+/// A byte buffer `[ptr, ptr + len)` in user space
+#[derive(Copy, Clone)]
+pub struct UserSlice {
+    token: usize,
+    ptr: usize,
+    len: usize,
+}
+
+impl UserSlice {
+    ///Create a `UserSlice` in the address space given by `token`
+    pub fn new(token: usize, ptr: *const u8, len: usize) -> Self {
+        Self {
+            token,
+            ptr: ptr as usize,
+            len,
+        }
+    }
+    ///Length of the buffer in bytes
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    ///Check whether the buffer is empty
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    /// Validate every page of the buffer and return the per-page kernel views.
+    /// `write` tells whether the kernel is going to write to the buffer.
+    pub fn buffers(&self, write: bool) -> SysResult<Vec<&'static mut [u8]>> {
+        let page_table = PageTable::from_token(self.token);
+        let mut start = self.ptr;
+        let end = start.checked_add(self.len).ok_or(SysError::EFAULT)?;
+        let mut v = Vec::new();
+        let mut pinned = Vec::new();
+        while start < end {
+            let ppn = translate_user_page(&page_table, start, write, &pinned)?;
+            let start_va = VirtAddr::from(start);
+            let mut vpn = start_va.floor();
+            pinned.push(vpn);
+            vpn.step();
+            let mut end_va: VirtAddr = vpn.into();
+            end_va = end_va.min(VirtAddr::from(end));
+            if end_va.page_offset() == 0 {
+                v.push(&mut ppn.get_bytes_array()[start_va.page_offset()..]);
+            } else {
+                v.push(&mut ppn.get_bytes_array()[start_va.page_offset()..end_va.page_offset()]);
+            }
+            start = end_va.into();
+        }
+        Ok(v)
+    }
+    ///Copy the whole buffer from user space
+    pub fn copy_from_user(&self) -> SysResult<Vec<u8>> {
+        let mut data = Vec::new();
+        data.try_reserve_exact(self.len)
+            .map_err(|_| SysError::ENOMEM)?;
+        data.resize(self.len, 0);
+        self.read_into(&mut data)?;
+        Ok(data)
+    }
+    ///Copy the first `dst.len()` bytes of the buffer from user space
+    pub fn read_into(&self, dst: &mut [u8]) -> SysResult<()> {
+        let src = Self::new(self.token, self.ptr as *const u8, dst.len().min(self.len));
+        let mut offset = 0;
+        for buffer in src.buffers(false)? {
+            dst[offset..offset + buffer.len()].copy_from_slice(buffer);
+            offset += buffer.len();
+        }
+        Ok(())
+    }
+    ///Copy `src` to the start of the buffer, returns the number of bytes copied
+    pub fn copy_to_user(&self, src: &[u8]) -> SysResult<usize> {
+        let dst = Self::new(self.token, self.ptr as *const u8, src.len().min(self.len));
+        let mut offset = 0;
+        for buffer in dst.buffers(true)? {
+            buffer.copy_from_slice(&src[offset..offset + buffer.len()]);
+            offset += buffer.len();
+        }
+        Ok(offset)
+    }
+}
+
+/// A typed pointer into user space
+pub struct UserPtr<T> {
+    token: usize,
+    ptr: usize,
+    _marker: PhantomData<T>,
+}
+
+impl<T: Copy> UserPtr<T> {
+    ///Create a `UserPtr` in the address space given by `token`
+    pub fn new(token: usize, ptr: *const T) -> Self {
+        Self {
+            token,
+            ptr: ptr as usize,
+            _marker: PhantomData,
+        }
+    }
+    ///Check whether the pointer is null
+    pub fn is_null(&self) -> bool {
+        self.ptr == 0
+    }
+    fn as_slice(&self) -> UserSlice {
+        UserSlice::new(self.token, self.ptr as *const u8, size_of::<T>())
+    }
+    ///Read a `T` from user space, the value may cross a page boundary
+    pub fn read(&self) -> SysResult<T> {
+        let mut value = MaybeUninit::<T>::uninit();
+        let bytes =
+            unsafe { core::slice::from_raw_parts_mut(value.as_mut_ptr() as *mut u8, size_of::<T>()) };
+        self.as_slice().read_into(bytes)?;
+        Ok(unsafe { value.assume_init() })
+    }
+    ///Write a `T` to user space, the value may cross a page boundary
+    pub fn write(&self, value: T) -> SysResult<()> {
+        let bytes =
+            unsafe { core::slice::from_raw_parts(&value as *const T as *const u8, size_of::<T>()) };
+        self.as_slice().copy_to_user(bytes)?;
+        Ok(())
+    }
+}
+
+/// A `\0`-terminated string in user space, at most [`USER_CSTR_MAX`] bytes long
+pub struct UserCStr {
+    token: usize,
+    ptr: usize,
+}
+
+impl UserCStr {
+    ///Create a `UserCStr` in the address space given by `token`
+    pub fn new(token: usize, ptr: *const u8) -> Self {
+        Self {
+            token,
+            ptr: ptr as usize,
+        }
+    }
+    /// Read the string, fails with `EFAULT` on a bad page and with `EINVAL`
+    /// if there is no `\0` within [`USER_CSTR_MAX`] bytes or it is not utf-8.
+    pub fn read(&self) -> SysResult<String> {
+        let page_table = PageTable::from_token(self.token);
+        let mut bytes = Vec::new();
+        let mut va = self.ptr;
+        loop {
+            let ppn = translate_user_page(&page_table, va, false, &[])?;
+            let start_va = VirtAddr::from(va);
+            // scan the rest of this page
+            for &ch in ppn.get_bytes_array()[start_va.page_offset()..].iter() {
+                if ch == 0 {
+                    return String::from_utf8(bytes).map_err(|_| SysError::EINVAL);
+                }
+                bytes.push(ch);
+                if bytes.len() >= USER_CSTR_MAX {
+                    return Err(SysError::EINVAL);
+                }
+            }
+            va = va - start_va.page_offset() + PAGE_SIZE;
+        }
+    }
+}
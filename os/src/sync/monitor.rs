@@ -50,6 +50,10 @@ impl HoareMonitor {
         drop(inner);
         res_id
     }
+    ///管程中条件变量的数目
+    pub fn res_sem_count(&self) -> usize {
+        self.inner_exclusive_access().res_sem_list.len()
+    }
     ///进入管程
     pub fn enter(&self) {
         let inner = self.inner_exclusive_access();
@@ -196,4 +200,4 @@ impl HoareMonitor {
         let mut inner =  self.inner_exclusive_access();
         inner.thread_count += num
     }
-}
\ No newline at end of file
+}
\ No newline at end of file
@@ -1,11 +1,18 @@
-use alloc::{collections::vec_deque::VecDeque, sync::Arc};
+use alloc::{
+    collections::vec_deque::VecDeque,
+    sync::{Arc, Weak},
+    vec::Vec,
+};
 
-use crate::task::{block_current_and_run_next, current_task, wakeup_task, ThreadControlBlock};
+use crate::task::{
+    block_current_and_run_next, current_task, requeue_task, wakeup_task, TaskStatus,
+    ThreadControlBlock,
+};
 
 use super::UPSafeCell;
 use core::cell::RefMut;
 
-///互斥锁
+///互斥锁，持有者继承等待者中最高的优先级，直到释放锁
 pub struct Mutex {
     inner: UPSafeCell<MutexInner>,
 }
@@ -14,6 +21,7 @@ pub struct Mutex {
 pub struct MutexInner {
     pub is_locked: bool, //互斥锁状态,当存在线程拥有锁时,值为true,否则为false
     pub waited_queue: VecDeque<Arc<ThreadControlBlock>>, //互斥锁队列
+    pub owner: Option<Weak<ThreadControlBlock>>, //持有锁的线程
 }
 
 impl Mutex {
@@ -25,6 +33,7 @@ impl Mutex {
                     MutexInner {
                         is_locked: false,
                         waited_queue: VecDeque::new(),
+                        owner: None,
                     }
                 )
             }
@@ -37,15 +46,17 @@ impl Mutex {
     }
 
     ///申请锁
-    pub fn lock(&self) {
+    pub fn lock(self: &Arc<Self>) {
+        let thread = current_task().unwrap();
         let mut is_locked = self.is_locked();
         //当有线程占有锁时，进入循环，当前线程阻塞
         while is_locked {
-            //将线程加入互斥锁队列，并阻塞该线程
-            let thread = current_task().unwrap();
+            //将线程加入互斥锁队列，持有者继承当前线程的优先级，并阻塞该线程
             let mut inner = self.inner_exclusive_access();
-            inner.waited_queue.push_back(thread.clone()); 
+            inner.waited_queue.push_back(thread.clone());
             drop(inner);
+            thread.inner_exclusive_access().blocked_on = Some(Arc::downgrade(self));
+            self.propagate_priority();
             block_current_and_run_next();
             //线程被唤醒后，需重复检查当前是否符合等待条件
             is_locked = self.is_locked();
@@ -53,6 +64,14 @@ impl Mutex {
         //当没有线程拥有锁时，当前线程占有锁
         let mut inner = self.inner_exclusive_access();
         inner.is_locked = true;
+        inner.owner = Some(Arc::downgrade(&thread));
+        drop(inner);
+        let mut thread_inner = thread.inner_exclusive_access();
+        thread_inner.blocked_on = None;
+        thread_inner.held_mutexes.push(Arc::downgrade(self));
+        drop(thread_inner);
+        //仍在等待的线程的优先级转由当前线程继承
+        update_inherited_priority(&thread);
     }
 
     ///释放锁
@@ -60,8 +79,20 @@ impl Mutex {
         //当前线程修改锁状态，释放锁
         let mut inner = self.inner_exclusive_access();
         inner.is_locked = false;
+        let owner = inner.owner.take().and_then(|owner| owner.upgrade());
+        drop(inner);
+        //不再继承这把锁的等待者的优先级，在唤醒等待者之前恢复，以便它能抢占当前线程
+        if let Some(owner) = owner {
+            owner
+                .inner_exclusive_access()
+                .held_mutexes
+                .retain(|mutex| !core::ptr::eq(mutex.as_ptr(), self));
+            update_inherited_priority(&owner);
+        }
         //当互斥锁队列中还存在等待线程时，唤醒第一个线程
-        if let Some(waited_thread) = inner.waited_queue.pop_front() {
+        let waited_thread = self.inner_exclusive_access().waited_queue.pop_front();
+        if let Some(waited_thread) = waited_thread {
+            waited_thread.inner_exclusive_access().blocked_on = None;
             wakeup_task(waited_thread);
         }
     }
@@ -70,4 +101,82 @@ impl Mutex {
     pub fn is_locked(&self) -> bool {
         self.inner_exclusive_access().is_locked
     }
+
+    ///持有锁的线程
+    fn owner(&self) -> Option<Arc<ThreadControlBlock>> {
+        self.inner_exclusive_access()
+            .owner
+            .as_ref()
+            .and_then(Weak::upgrade)
+    }
+
+    ///等待者中最高的实时优先级与优先级，没有等待者时均为 0
+    fn waiters_priority(&self) -> (usize, usize) {
+        let inner = self.inner_exclusive_access();
+        inner
+            .waited_queue
+            .iter()
+            .fold((0, 0), |(rt_priority, priority), waiter| {
+                let sched = &waiter.inner_exclusive_access().sched;
+                let waiter_rt_priority = if sched.is_realtime() {
+                    sched.effective_rt_priority()
+                } else {
+                    0
+                };
+                (
+                    rt_priority.max(waiter_rt_priority),
+                    priority.max(sched.effective_priority()),
+                )
+            })
+    }
+
+    ///沿着“锁的持有者正在等待的锁”传递优先级，直到某个持有者的优先级不再改变。
+    ///死锁形成的环上优先级同样会停止改变，因此循环总会结束
+    fn propagate_priority(self: &Arc<Self>) {
+        let mut mutex = self.clone();
+        while let Some(owner) = mutex.owner() {
+            if !update_inherited_priority(&owner) {
+                break;
+            }
+            let blocked_on = owner
+                .inner_exclusive_access()
+                .blocked_on
+                .as_ref()
+                .and_then(Weak::upgrade);
+            match blocked_on {
+                Some(next) => mutex = next,
+                None => break,
+            }
+        }
+    }
+}
+
+///根据 `thread` 持有的锁的等待者重新计算它继承的优先级，返回是否改变。
+///就绪的线程按新的优先级重新排队
+fn update_inherited_priority(thread: &Arc<ThreadControlBlock>) -> bool {
+    let held_mutexes: Vec<Arc<Mutex>> = thread
+        .inner_exclusive_access()
+        .held_mutexes
+        .iter()
+        .filter_map(Weak::upgrade)
+        .collect();
+    let (mut rt_priority, mut priority) = (0, 0);
+    for mutex in held_mutexes {
+        let (waiters_rt_priority, waiters_priority) = mutex.waiters_priority();
+        rt_priority = rt_priority.max(waiters_rt_priority);
+        priority = priority.max(waiters_priority);
+    }
+    let mut thread_inner = thread.inner_exclusive_access();
+    let sched = &mut thread_inner.sched;
+    if sched.inherited_rt_priority == rt_priority && sched.inherited_priority == priority {
+        return false;
+    }
+    sched.inherited_rt_priority = rt_priority;
+    sched.inherited_priority = priority;
+    let ready = thread_inner.task_status == TaskStatus::Ready;
+    drop(thread_inner);
+    if ready {
+        requeue_task(thread);
+    }
+    true
 }
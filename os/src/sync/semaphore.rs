@@ -58,9 +58,11 @@ impl Semaphore {
         inner.value += 1;
         //当信号量队列中还存在等待线程时，唤醒第一个线程使之得到该资源
         if inner.value <= 0 {
-            let thread = inner.waited_queue.pop_front().unwrap();
-            drop(inner);
-            wakeup_task(thread);
+            //等待线程可能已被管程检测杀死，此时队列为空
+            if let Some(thread) = inner.waited_queue.pop_front() {
+                drop(inner);
+                wakeup_task(thread);
+            }
         }
     }
-}
\ No newline at end of file
+}
\ No newline at end of file
@@ -0,0 +1,44 @@
+//! 系统调用错误码
+//!
+//! 所有系统调用在出错时返回对应错误码的相反数，编号与 Linux 保持一致，
+//! 用户态的 `user_lib::SysError` 与此处一一对应。
+
+///系统调用错误码
+#[allow(unused, clippy::upper_case_acronyms)]
+#[repr(isize)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SysError {
+    EPERM = 1,    //操作不允许
+    ENOENT = 2,   //文件或程序不存在
+    ESRCH = 3,    //进程或线程不存在
+    EINTR = 4,    //被中断
+    EIO = 5,      //输入输出错误
+    EBADF = 9,    //无效的文件描述符
+    ECHILD = 10,  //没有可等待的子进程
+    EAGAIN = 11,  //资源暂时不可用
+    ENOMEM = 12,  //内存不足
+    EACCES = 13,  //权限不足
+    EFAULT = 14,  //非法的用户地址
+    EBUSY = 16,   //资源忙
+    EEXIST = 17,  //文件已存在
+    ENOTDIR = 20, //不是目录
+    EISDIR = 21,  //是目录
+    EINVAL = 22,  //无效参数
+    EMFILE = 24,  //打开的文件过多
+    ENOSPC = 28,  //设备空间不足
+    ESPIPE = 29,  //非法的定位操作
+    EPIPE = 32,   //管道读端已关闭
+    EDEADLK = 35, //检测到死锁
+    ENAMETOOLONG = 36, //文件名过长
+    ENOSYS = 38,  //系统调用未实现
+}
+
+impl SysError {
+    ///转换为系统调用的返回值
+    pub fn as_ret(self) -> isize {
+        -(self as isize)
+    }
+}
+
+///系统调用处理函数的返回类型
+pub type SysResult<T = isize> = Result<T, SysError>;
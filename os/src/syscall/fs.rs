@@ -1,49 +1,124 @@
 //! File and filesystem-related syscalls
-use crate::mm::translated_byte_buffer;
-use crate::sbi::console_getchar;
-use crate::task::{current_user_token, suspend_current_and_run_next};
-
-const FD_STDIN: usize = 0;
-const FD_STDOUT: usize = 1;
-
-pub fn sys_write(fd: usize, buf: *const u8, len: usize) -> isize {
-    match fd {
-        FD_STDOUT => {
-            let buffers = translated_byte_buffer(current_user_token(), buf, len);
-            for buffer in buffers {
-                print!("{}", core::str::from_utf8(buffer).unwrap());
-            }
-            len as isize
-        }
-        _ => {
-            panic!("Unsupported fd in sys_write!");
-        }
+use super::{SysError, SysResult};
+use crate::config::MAX_FD_NUM;
+use crate::fs::{make_pipe, open_file, unlink_file, OpenFlags, Stat};
+use crate::mm::{UserCStr, UserPtr, UserSlice};
+use crate::task::{current_user_process, current_user_token};
+
+pub fn sys_write(fd: usize, buf: *const u8, len: usize) -> SysResult {
+    let process = current_user_process();
+    let inner = process.inner_exclusive_access();
+    let file = inner.get_file(fd)?;
+    if !file.writable() {
+        return Err(SysError::EBADF);
     }
+    let token = inner.get_user_token();
+    // release current PCB, writing may block
+    drop(inner);
+    Ok(file.write(UserSlice::new(token, buf, len))? as isize)
 }
 
-pub fn sys_read(fd: usize, buf: *const u8, len: usize) -> isize {
-    match fd {
-        FD_STDIN => {
-            assert_eq!(len, 1, "Only support len = 1 in sys_read!");
-            let mut c: usize;
-            loop {
-                c = console_getchar();
-                if c == 0 {
-                    suspend_current_and_run_next();
-                    continue;
-                } else {
-                    break;
-                }
-            }
-            let ch = c as u8;
-            let mut buffers = translated_byte_buffer(current_user_token(), buf, len);
-            unsafe {
-                buffers[0].as_mut_ptr().write_volatile(ch);
-            }
-            1
-        }
-        _ => {
-            panic!("Unsupported fd in sys_read!");
-        }
+pub fn sys_read(fd: usize, buf: *const u8, len: usize) -> SysResult {
+    let process = current_user_process();
+    let inner = process.inner_exclusive_access();
+    let file = inner.get_file(fd)?;
+    if !file.readable() {
+        return Err(SysError::EBADF);
     }
+    let token = inner.get_user_token();
+    // release current PCB, reading may block
+    drop(inner);
+    Ok(file.read(UserSlice::new(token, buf, len))? as isize)
+}
+
+pub fn sys_open(path: *const u8, flags: u32) -> SysResult {
+    let token = current_user_token();
+    let path = UserCStr::new(token, path).read()?;
+    let flags = OpenFlags::from_bits(flags).ok_or(SysError::EINVAL)?;
+    let file = open_file(path.as_str(), flags)?;
+    let process = current_user_process();
+    let mut inner = process.inner_exclusive_access();
+    let fd = inner.alloc_fd()?;
+    inner.fd_table[fd] = Some(file);
+    Ok(fd as isize)
+}
+
+pub fn sys_close(fd: usize) -> SysResult {
+    let process = current_user_process();
+    let mut inner = process.inner_exclusive_access();
+    inner.get_file(fd)?;
+    inner.fd_table[fd].take();
+    Ok(0)
+}
+
+/// Duplicate `fd` to the lowest free descriptor
+pub fn sys_dup(fd: usize) -> SysResult {
+    let process = current_user_process();
+    let mut inner = process.inner_exclusive_access();
+    let file = inner.get_file(fd)?;
+    let new_fd = inner.alloc_fd()?;
+    inner.fd_table[new_fd] = Some(file);
+    Ok(new_fd as isize)
+}
+
+/// Duplicate `old_fd` to `new_fd`, closing the file previously at `new_fd`
+pub fn sys_dup2(old_fd: usize, new_fd: usize) -> SysResult {
+    let process = current_user_process();
+    let mut inner = process.inner_exclusive_access();
+    let file = inner.get_file(old_fd)?;
+    if new_fd >= MAX_FD_NUM {
+        return Err(SysError::EBADF);
+    }
+    if old_fd == new_fd {
+        return Ok(new_fd as isize);
+    }
+    if new_fd >= inner.fd_table.len() {
+        inner.fd_table.resize(new_fd + 1, None);
+    }
+    inner.fd_table[new_fd] = Some(file);
+    Ok(new_fd as isize)
+}
+
+/// Create a pipe, its read end and write end are stored to `pipe[0]` and `pipe[1]`
+pub fn sys_pipe(pipe: *mut usize) -> SysResult {
+    let token = current_user_token();
+    let fds = UserPtr::new(token, pipe as *const [usize; 2]);
+    // make sure the result can be stored before any descriptor is allocated
+    fds.write([0; 2])?;
+    let process = current_user_process();
+    let mut inner = process.inner_exclusive_access();
+    let (pipe_read, pipe_write) = make_pipe();
+    let read_fd = inner.alloc_fd()?;
+    inner.fd_table[read_fd] = Some(pipe_read);
+    let write_fd = match inner.alloc_fd() {
+        Ok(fd) => fd,
+        Err(err) => {
+            inner.fd_table[read_fd] = None;
+            return Err(err);
+        }
+    };
+    inner.fd_table[write_fd] = Some(pipe_write);
+    drop(inner);
+    fds.write([read_fd, write_fd])?;
+    Ok(0)
+}
+
+/// Move the offset of `fd`, `whence` is one of `SEEK_SET`, `SEEK_CUR` and `SEEK_END`
+pub fn sys_lseek(fd: usize, offset: isize, whence: usize) -> SysResult {
+    let file = current_user_process().inner_exclusive_access().get_file(fd)?;
+    Ok(file.seek(offset, whence)? as isize)
+}
+
+pub fn sys_fstat(fd: usize, st: *mut Stat) -> SysResult {
+    let token = current_user_token();
+    let file = current_user_process().inner_exclusive_access().get_file(fd)?;
+    UserPtr::new(token, st as *const Stat).write(file.stat())?;
+    Ok(0)
+}
+
+pub fn sys_unlink(path: *const u8) -> SysResult {
+    let token = current_user_token();
+    let path = UserCStr::new(token, path).read()?;
+    unlink_file(path.as_str())?;
+    Ok(0)
 }
@@ -0,0 +1,199 @@
+//! Memory-related syscalls
+use super::{SysError, SysResult};
+use crate::config::{MMAP_BASE, PAGE_SIZE, USER_SPACE_END, USER_STACK_BASE};
+use crate::mm::{MapPermission, MemStat, MemorySet, SharedMemory, UserPtr, VirtAddr, VirtPageNum};
+use crate::task::{current_user_process, current_user_token};
+use alloc::vec::Vec;
+
+bitflags! {
+    /// Protection of the memory mapped by `mmap`
+    pub struct MmapProt: usize {
+        /// Readable
+        const READ = 1 << 0;
+        /// Writable, implies `READ`
+        const WRITE = 1 << 1;
+        /// Executable
+        const EXEC = 1 << 2;
+    }
+}
+
+impl MmapProt {
+    /// Permission of the mapped area, RISC-V has no write-only pages. The
+    /// pages can not be accessed at all if it is empty.
+    fn map_permission(&self) -> MapPermission {
+        let mut permission = MapPermission::U;
+        if self.intersects(Self::READ | Self::WRITE) {
+            permission |= MapPermission::R;
+        }
+        if self.contains(Self::WRITE) {
+            permission |= MapPermission::W;
+        }
+        if self.contains(Self::EXEC) {
+            permission |= MapPermission::X;
+        }
+        permission
+    }
+}
+
+/// Check that `[addr, addr + len)` is a non-empty page-aligned range of user
+/// space, `len` is rounded up to whole pages. Returns its pages.
+fn user_page_range(addr: usize, len: usize) -> SysResult<(VirtPageNum, VirtPageNum)> {
+    if addr % PAGE_SIZE != 0 || len == 0 {
+        return Err(SysError::EINVAL);
+    }
+    let end = addr
+        .checked_add(len)
+        .filter(|end| *end <= USER_SPACE_END)
+        .ok_or(SysError::ENOMEM)?;
+    Ok((VirtAddr::from(addr).floor(), VirtAddr::from(end).ceil()))
+}
+
+/// Pick the pages to map `len` bytes at `addr`, or at a free address chosen
+/// by the kernel if `addr` is 0. The pages above `USER_STACK_BASE` are kept
+/// for the stacks of threads created later, asking for them fails with
+/// `ENOMEM`. Fails with `EEXIST` if the pages at `addr` are mapped already.
+fn free_page_range(
+    memory_set: &MemorySet,
+    addr: usize,
+    len: usize,
+) -> SysResult<(VirtPageNum, VirtPageNum)> {
+    let stack_base = VirtAddr::from(USER_STACK_BASE).floor();
+    if addr == 0 {
+        // look for a free range as long as `[0, len)`
+        let (_, pages) = user_page_range(0, len)?;
+        let start = memory_set
+            .find_free_range(VirtAddr::from(MMAP_BASE).floor(), stack_base, pages.0)
+            .ok_or(SysError::ENOMEM)?;
+        Ok((start, VirtPageNum(start.0 + pages.0)))
+    } else {
+        let (start, end) = user_page_range(addr, len)?;
+        if end > stack_base {
+            return Err(SysError::ENOMEM);
+        }
+        if memory_set.overlaps(start, end) {
+            return Err(SysError::EEXIST);
+        }
+        Ok((start, end))
+    }
+}
+
+/// Map `len` bytes of zeroed memory at `addr`, or at a free address chosen by
+/// the kernel if `addr` is 0. The pages get their frames on first touch.
+///
+/// Returns the start address, `EINVAL` for a bad `prot`, an unaligned `addr`
+/// or an empty range, `EEXIST` if the range overlaps mapped memory, and
+/// `ENOMEM` if there is no room for it below the thread stacks.
+pub fn sys_mmap(addr: usize, len: usize, prot: usize) -> SysResult {
+    let prot = MmapProt::from_bits(prot)
+        .filter(|prot| !prot.is_empty())
+        .ok_or(SysError::EINVAL)?;
+    let process = current_user_process();
+    let mut inner = process.inner_exclusive_access();
+    let memory_set = &mut inner.memory_set;
+    let (start, end) = free_page_range(memory_set, addr, len)?;
+    memory_set.insert_lazy_area(start.into(), end.into(), prot.map_permission());
+    let start_va: VirtAddr = start.into();
+    Ok(start_va.0 as isize)
+}
+
+/// Attach `len` bytes of the shared memory named `key` readable and writable
+/// at `addr`, or at a free address chosen by the kernel if `addr` is 0. The
+/// shared memory is created zeroed if it is not attached anywhere, `key` 0
+/// always creates a new one, shared only with the children forked later.
+/// It stays shared across fork, `munmap` detaches it.
+///
+/// Returns the start address, `EINVAL` if the shared memory is shorter than
+/// `len`, and the errors of `mmap` otherwise.
+pub fn sys_shmat(key: usize, len: usize, addr: usize) -> SysResult {
+    let process = current_user_process();
+    let mut inner = process.inner_exclusive_access();
+    let memory_set = &mut inner.memory_set;
+    let (start, end) = free_page_range(memory_set, addr, len)?;
+    let pages = end.0 - start.0;
+    let shared_memory = if key == 0 {
+        SharedMemory::new(pages)
+    } else {
+        SharedMemory::get_or_create(key, pages)
+    }
+    .ok_or(SysError::ENOMEM)?;
+    if shared_memory.pages() < pages {
+        return Err(SysError::EINVAL);
+    }
+    memory_set.attach_shared(
+        start,
+        pages,
+        shared_memory,
+        (MmapProt::READ | MmapProt::WRITE).map_permission(),
+    )?;
+    let start_va: VirtAddr = start.into();
+    Ok(start_va.0 as isize)
+}
+
+/// Unmap the pages in `[addr, addr + len)`, an area partly in the range
+/// keeps the rest of its pages. Pages not mapped are skipped.
+pub fn sys_munmap(addr: usize, len: usize) -> SysResult {
+    let (start, end) = user_page_range(addr, len)?;
+    current_user_process()
+        .inner_exclusive_access()
+        .memory_set
+        .remove_user_range(start, end);
+    Ok(0)
+}
+
+/// Move the program break by `increment` bytes, returns the old break or
+/// `ENOMEM`. The new heap pages get their frames on first touch.
+pub fn sys_sbrk(increment: isize) -> SysResult {
+    current_user_process()
+        .inner_exclusive_access()
+        .memory_set
+        .sbrk(increment)
+        .map(|brk| brk as isize)
+        .ok_or(SysError::ENOMEM)
+}
+
+/// Change the protection of the pages in `[addr, addr + len)` to `prot`,
+/// which may be empty to make them inaccessible.
+///
+/// Fails with `ENOMEM` if some page is not mapped, and with `EACCES` for the
+/// upper half of the address space, which belongs to the kernel.
+pub fn sys_mprotect(addr: usize, len: usize, prot: usize) -> SysResult {
+    let prot = MmapProt::from_bits(prot).ok_or(SysError::EINVAL)?;
+    if addr % PAGE_SIZE != 0 || len == 0 {
+        return Err(SysError::EINVAL);
+    }
+    let end = addr.checked_add(len).ok_or(SysError::ENOMEM)?;
+    if end > USER_SPACE_END {
+        return Err(SysError::EACCES);
+    }
+    current_user_process()
+        .inner_exclusive_access()
+        .memory_set
+        .mprotect(
+            VirtAddr::from(addr).floor(),
+            VirtAddr::from(end).ceil(),
+            prot.map_permission(),
+        )?;
+    Ok(0)
+}
+
+/// Write the memory usage of the calling process and the whole system to
+/// `stat`, see [`MemStat`].
+pub fn sys_mem_stat(stat: *mut MemStat) -> SysResult {
+    let process = current_user_process();
+    let process_inner = process.inner_exclusive_access();
+    // exited threads have given their stacks back already
+    let ustack_bottoms: Vec<VirtPageNum> = process_inner
+        .threads
+        .iter()
+        .flatten()
+        .filter_map(|thread| {
+            let thread_inner = thread.inner_exclusive_access();
+            let res = thread_inner.res.as_ref()?;
+            Some(VirtAddr::from(res.ustack_bottom()).floor())
+        })
+        .collect();
+    let mem_stat = MemStat::new(&process_inner.memory_set, &ustack_bottoms);
+    drop(process_inner);
+    UserPtr::new(current_user_token(), stat as *const MemStat).write(mem_stat)?;
+    Ok(0)
+}
@@ -9,18 +9,39 @@
 //! For clarity, each single syscall is implemented as its own function, named
 //! `sys_` then the name of the syscall. You can find functions like this in
 //! submodules, and you should also implement syscalls this way.
+const SYSCALL_DUP: usize = 23;
+const SYSCALL_DUP2: usize = 24;
+const SYSCALL_UNLINK: usize = 35;
+const SYSCALL_OPEN: usize = 56;
+const SYSCALL_CLOSE: usize = 57;
+const SYSCALL_PIPE: usize = 59;
+const SYSCALL_LSEEK: usize = 62;
 const SYSCALL_READ: usize = 63;
 const SYSCALL_WRITE: usize = 64;
+const SYSCALL_FSTAT: usize = 80;
 const SYSCALL_EXIT: usize = 93;
+const SYSCALL_SCHED_SETSCHEDULER: usize = 119;
 const SYSCALL_YIELD: usize = 124;
+const SYSCALL_SET_PRIORITY: usize = 140;
 const SYSCALL_GET_TIME: usize = 169;
 const SYSCALL_GETPID: usize = 172;
+const SYSCALL_SHMAT: usize = 196;
+const SYSCALL_SBRK: usize = 214;
+const SYSCALL_MUNMAP: usize = 215;
 const SYSCALL_FORK: usize = 220;
 const SYSCALL_EXEC: usize = 221;
+const SYSCALL_MMAP: usize = 222;
+const SYSCALL_MPROTECT: usize = 226;
 const SYSCALL_WAITPID: usize = 260;
 const SYSCALL_THREAD_CREATE: usize = 1000;
 const SYSCALL_GETTID: usize = 1001;
 const SYSCALL_WAITTID: usize = 1002;
+const SYSCALL_THREAD_EXIT: usize = 1003;
+const SYSCALL_THREAD_DETACH: usize = 1004;
+const SYSCALL_MEM_STAT: usize = 2000;
+const SYSCALL_SET_PERIODIC: usize = 2001;
+const SYSCALL_WAIT_NEXT_PERIOD: usize = 2002;
+const SYSCALL_PERIODIC_STAT: usize = 2003;
 const SYSCALL_SLEEP: usize = 101;
 const SYSCALL_MUTEX_CREATE: usize = 501;
 const SYSCALL_MUTEX_LOCK: usize = 502;
@@ -39,47 +60,82 @@ const SYSCALL_MONITOR_SIGNAL: usize = 515;
 const SYSCALL_MONITOR_DESTROY: usize = 516;
 const SYSCALL_MONITOR_CHECK: usize = 517;
 
+mod errno;
 mod fs;
+mod mm;
 mod process;
+mod sched;
 mod thread;
 mod sync;
 
+pub use errno::{SysError, SysResult};
+use crate::fs::Stat;
+use crate::mm::MemStat;
+use crate::task::PeriodicStat;
 use fs::*;
+use mm::*;
 use process::*;
+use sched::*;
 use thread::*;
 use sync::*;
-/// handle syscall exception with `syscall_id` and other arguments
+/// handle syscall exception with `syscall_id` and other arguments,
+/// errors are returned to user space as negative [`SysError`] codes
 pub fn syscall(syscall_id: usize, args: [usize; 3]) -> isize {
-    match syscall_id {
+    let result = match syscall_id {
+        SYSCALL_DUP => sys_dup(args[0]),
+        SYSCALL_DUP2 => sys_dup2(args[0], args[1]),
+        SYSCALL_UNLINK => sys_unlink(args[0] as *const u8),
+        SYSCALL_OPEN => sys_open(args[0] as *const u8, args[1] as u32),
+        SYSCALL_CLOSE => sys_close(args[0]),
+        SYSCALL_PIPE => sys_pipe(args[0] as *mut usize),
+        SYSCALL_LSEEK => sys_lseek(args[0], args[1] as isize, args[2]),
         SYSCALL_READ => sys_read(args[0], args[1] as *const u8, args[2]),
         SYSCALL_WRITE => sys_write(args[0], args[1] as *const u8, args[2]),
+        SYSCALL_FSTAT => sys_fstat(args[0], args[1] as *mut Stat),
         SYSCALL_EXIT => sys_exit(args[0] as i32),
+        SYSCALL_SCHED_SETSCHEDULER => sys_sched_setscheduler(args[0], args[1]),
         SYSCALL_YIELD => sys_yield(),
+        SYSCALL_SET_PRIORITY => sys_set_priority(args[0] as isize),
         SYSCALL_GET_TIME => sys_get_time(),
         SYSCALL_GETPID => sys_getpid(),
+        SYSCALL_SHMAT => sys_shmat(args[0], args[1], args[2]),
+        SYSCALL_SBRK => sys_sbrk(args[0] as isize),
+        SYSCALL_MUNMAP => sys_munmap(args[0], args[1]),
         SYSCALL_FORK => sys_fork(),
         SYSCALL_EXEC => sys_exec(args[0] as *const u8),
-        SYSCALL_WAITPID => sys_waitpid(args[0] as isize, args[1] as *mut i32),
+        SYSCALL_MMAP => sys_mmap(args[0], args[1], args[2]),
+        SYSCALL_MPROTECT => sys_mprotect(args[0], args[1], args[2]),
+        SYSCALL_WAITPID => sys_waitpid(args[0] as isize, args[1] as *mut i32, args[2]),
         SYSCALL_THREAD_CREATE => sys_thread_create(args[0], args[1]),
         SYSCALL_GETTID => sys_gettid(),
-        SYSCALL_WAITTID => sys_waittid(args[0]) as isize,
+        SYSCALL_WAITTID => sys_waittid(args[0]),
+        SYSCALL_THREAD_EXIT => sys_thread_exit(args[0] as i32),
+        SYSCALL_THREAD_DETACH => sys_thread_detach(args[0]),
+        SYSCALL_MEM_STAT => sys_mem_stat(args[0] as *mut MemStat),
+        SYSCALL_SET_PERIODIC => sys_set_periodic(args[0], args[1], args[2]),
+        SYSCALL_WAIT_NEXT_PERIOD => sys_wait_next_period(),
+        SYSCALL_PERIODIC_STAT => sys_periodic_stat(args[0] as *mut PeriodicStat),
         SYSCALL_SLEEP => sys_sleep(args[0]),
-        SYSCALL_MUTEX_CREATE => sys_mutex_create() as isize,
+        SYSCALL_MUTEX_CREATE => sys_mutex_create(),
         SYSCALL_MUTEX_LOCK => sys_mutex_lock(args[0]),
         SYSCALL_MUTEX_UNLOCK => sys_mutex_unlock(args[0]),
-        SYSCALL_SEM_CREATE => sys_sem_create(args[0] as isize) as isize,
+        SYSCALL_SEM_CREATE => sys_sem_create(args[0] as isize),
         SYSCALL_SEM_WAIT => sys_sem_wait(args[0]),
         SYSCALL_SEM_POST => sys_sem_post(args[0]),
         SYSCALL_MUTEX_DESTROY => sys_mutex_destroy(args[0]),
         SYSCALL_SEM_DESTROY => sys_sem_destroy(args[0]),
-        SYSCALL_MONITOR_CREATE => sys_monitor_create() as isize,
+        SYSCALL_MONITOR_CREATE => sys_monitor_create(),
         SYSCALL_MONITOR_ENTER => sys_monitor_enter(args[0]),
         SYSCALL_MONITOR_LEAVE => sys_monitor_leave(args[0]),
-        SYSCALL_MONITOR_CREATE_RES_SEM => sys_monitor_create_res_sem(args[0]) as isize,
+        SYSCALL_MONITOR_CREATE_RES_SEM => sys_monitor_create_res_sem(args[0]),
         SYSCALL_MONITOR_WAIT => sys_monitor_wait(args[0], args[1]),
         SYSCALL_MONITOR_SIGNAL => sys_monitor_signal(args[0], args[1]),
         SYSCALL_MONITOR_CHECK => sys_monitor_check(args[0]),
         SYSCALL_MONITOR_DESTROY => sys_monitor_destroy(args[0]),
-        _ => panic!("Unsupported syscall_id: {}", syscall_id),
+        _ => Err(SysError::ENOSYS),
+    };
+    match result {
+        Ok(ret) => ret,
+        Err(err) => err.as_ret(),
     }
 }
@@ -1,33 +1,39 @@
-use crate::loader::get_app_data_by_name;
-use crate::mm::{translated_refmut, translated_str};
+use super::{SysError, SysResult};
+use crate::fs::{open_file, OpenFlags};
+use crate::mm::{UserCStr, UserPtr};
 use crate::task::{
-    current_task, current_user_process, current_user_token, exit_current_and_run_next, suspend_current_and_run_next
+    block_current_and_run_next, current_task, current_user_process, current_user_token,
+    exit_current_and_run_next, suspend_current_and_run_next,
 };
 use crate::timer::get_time_ms;
 use alloc::sync::Arc;
 
-///线程退出
+///进程退出，可由进程中任一线程调用
 pub fn sys_exit(exit_code: i32) -> ! {
     exit_current_and_run_next(exit_code);
     panic!("Unreachable in sys_exit!");
 }
 
-pub fn sys_yield() -> isize {
+pub fn sys_yield() -> SysResult {
     suspend_current_and_run_next();
-    0
+    Ok(0)
 }
 
-pub fn sys_get_time() -> isize {
-    get_time_ms() as isize
+pub fn sys_get_time() -> SysResult {
+    Ok(get_time_ms() as isize)
 }
 
-pub fn sys_getpid() -> isize {
-    current_user_process().getpid() as isize
+pub fn sys_getpid() -> SysResult {
+    Ok(current_user_process().getpid() as isize)
 }
 
-pub fn sys_fork() -> isize {
+pub fn sys_fork() -> SysResult {
     let current_process = current_user_process();
-    let new_process = current_process.fork();
+    // only a single-threaded process can be forked
+    if current_process.inner_exclusive_access().thread_count() != 1 {
+        return Err(SysError::EBUSY);
+    }
+    let new_process = current_process.fork()?;
     let new_pid = new_process.pid.0;
     // modify trap context of new_task, because it returns immediately after switching
     let trap_cx = new_process
@@ -38,52 +44,76 @@ pub fn sys_fork() -> isize {
     // we do not have to move to next instruction since we have done it before
     // for child process, fork returns 0
     trap_cx.x[10] = 0;
-    new_pid as isize
+    Ok(new_pid as isize)
 }
 
-pub fn sys_exec(path: *const u8) -> isize {
+pub fn sys_exec(path: *const u8) -> SysResult {
     let token = current_user_token();
-    let path = translated_str(token, path);
-    if let Some(data) = get_app_data_by_name(path.as_str()) {
-        let task = current_task().unwrap();
-        task.process.upgrade().unwrap().exec(data);
-        0
-    } else {
-        -1
+    let path = UserCStr::new(token, path).read()?;
+    let app_inode = open_file(path.as_str(), OpenFlags::RDONLY)?;
+    let all_data = app_inode.read_all()?;
+    let task = current_task().unwrap();
+    let process = task.process.upgrade().unwrap();
+    // exec replaces the whole address space, other threads would be left dangling
+    if process.inner_exclusive_access().thread_count() != 1 {
+        return Err(SysError::EBUSY);
     }
+    process.exec(all_data.as_slice())?;
+    Ok(0)
 }
 
-/// If there is not a child process whose pid is same as given, return -1.
-/// Else if there is a child process but it is still running, return -2.
-pub fn sys_waitpid(pid: isize, exit_code_ptr: *mut i32) -> isize {
+/// Return immediately with 0 if no matching child has exited yet
+pub const WNOHANG: usize = 1;
+
+/// If there is not a child process whose pid is same as given, return `ECHILD`.
+/// Else if there is a child process but it is still running, block the caller
+/// until a child exits, or return 0 at once when `WNOHANG` is given.
+pub fn sys_waitpid(pid: isize, exit_code_ptr: *mut i32, options: usize) -> SysResult {
+    if options & !WNOHANG != 0 {
+        return Err(SysError::EINVAL);
+    }
     let process = current_user_process();
-    // ---- access current TCB exclusively
-    let mut inner = process.inner_exclusive_access();
-    if !inner
-        .children
-        .iter()
-        .any(|p| pid == -1 || pid as usize == p.getpid())
-    {
-        return -1;
+    loop {
+        // ---- access current PCB exclusively
+        let mut inner = process.inner_exclusive_access();
+        if !inner
+            .children
+            .iter()
+            .any(|p| pid == -1 || pid as usize == p.getpid())
+        {
+            return Err(SysError::ECHILD);
+            // ---- release current PCB
+        }
+        let pair = inner.children.iter().enumerate().find(|(_, p)| {
+            // ++++ temporarily access child PCB lock exclusively
+            p.inner_exclusive_access().is_zombie && (pid == -1 || pid as usize == p.getpid())
+            // ++++ release child PCB
+        });
+        if let Some((idx, child)) = pair {
+            // ++++ temporarily access child TCB exclusively
+            let exit_code = child.inner_exclusive_access().exit_code;
+            // ++++ release child PCB
+            // report the exit code first, the child is kept as a zombie on EFAULT
+            let exit_code_ptr = UserPtr::new(inner.memory_set.token(), exit_code_ptr);
+            // ---- release current PCB, writing may copy a page shared since fork
+            drop(inner);
+            if !exit_code_ptr.is_null() {
+                exit_code_ptr.write(exit_code)?;
+            }
+            let mut inner = process.inner_exclusive_access();
+            let child = inner.children.remove(idx);
+            // confirm that child will be deallocated after removing from children list
+            assert_eq!(Arc::strong_count(&child), 1);
+            let found_pid = child.getpid();
+            return Ok(found_pid as isize);
+        }
+        if options & WNOHANG != 0 {
+            return Ok(0);
+        }
+        // sleep until one of the children exits, then check again
+        inner.child_exit_waiters.push_back(current_task().unwrap());
+        drop(inner);
         // ---- release current PCB
+        block_current_and_run_next();
     }
-    let pair = inner.children.iter().enumerate().find(|(_, p)| {
-        // ++++ temporarily access child PCB lock exclusively
-        p.inner_exclusive_access().is_zombie && (pid == -1 || pid as usize == p.getpid())
-        // ++++ release child PCB
-    });
-    if let Some((idx, _)) = pair {
-        let child = inner.children.remove(idx);
-        // confirm that child will be deallocated after removing from children list
-        assert_eq!(Arc::strong_count(&child), 1);
-        let found_pid = child.getpid();
-        // ++++ temporarily access child TCB exclusively
-        let exit_code = child.inner_exclusive_access().exit_code;
-        // ++++ release child PCB
-        *translated_refmut(inner.memory_set.token(), exit_code_ptr) = exit_code;
-        found_pid as isize
-    } else {
-        -2
-    }
-    // ---- release current PCB lock automatically
 }
@@ -0,0 +1,95 @@
+//! Scheduling-related syscalls
+use super::{SysError, SysResult};
+use crate::mm::UserPtr;
+use crate::task::{
+    block_current_and_run_next, current_task, current_user_token, deadline_bandwidth, Periodic,
+    PeriodicStat, SchedPolicy, MAX_DEADLINE_BANDWIDTH, MAX_PERIOD, MAX_RT_PRIORITY, MIN_PRIORITY,
+};
+use crate::timer::{add_timer, get_time_ms};
+
+///设置当前线程的优先级，返回设置的优先级，小于 `MIN_PRIORITY` 时返回 `EINVAL`
+pub fn sys_set_priority(prio: isize) -> SysResult {
+    if prio < MIN_PRIORITY as isize {
+        return Err(SysError::EINVAL);
+    }
+    current_task()
+        .unwrap()
+        .inner_exclusive_access()
+        .sched
+        .priority = prio as usize;
+    Ok(prio)
+}
+
+///把当前线程移入调度类别 `policy`，实时线程的优先级为 1 到 `MAX_RT_PRIORITY`，
+///普通线程为 0，否则返回 `EINVAL`。成为普通线程后，若有就绪的实时线程，
+///下一次时钟中断时让出处理器
+pub fn sys_sched_setscheduler(policy: usize, priority: usize) -> SysResult {
+    let policy = SchedPolicy::from_raw(policy).ok_or(SysError::EINVAL)?;
+    let valid = if policy.is_realtime() {
+        (1..=MAX_RT_PRIORITY).contains(&priority)
+    } else {
+        priority == 0
+    };
+    if !valid {
+        return Err(SysError::EINVAL);
+    }
+    let task = current_task().unwrap();
+    let mut task_inner = task.inner_exclusive_access();
+    task_inner.sched.policy = policy;
+    task_inner.sched.rt_priority = priority;
+    task_inner.sched.rr_slice_used = 0;
+    task_inner.sched.periodic = None;
+    Ok(0)
+}
+
+///把当前线程声明为周期线程，从现在开始每 `period` 毫秒一个周期，每个周期中最多运行
+///`budget` 毫秒，并应在周期开始后 `deadline` 毫秒内完成。`sched_setscheduler`
+///使它不再是周期线程。
+///
+///参数不满足 `0 < budget <= deadline <= period <= MAX_PERIOD` 时返回 `EINVAL`，
+///所有周期线程的带宽之和将超过 `MAX_DEADLINE_BANDWIDTH` 时返回 `EBUSY`
+pub fn sys_set_periodic(period: usize, budget: usize, deadline: usize) -> SysResult {
+    if budget == 0 || budget > deadline || deadline > period || period > MAX_PERIOD {
+        return Err(SysError::EINVAL);
+    }
+    let task = current_task().unwrap();
+    let periodic = Periodic::new(period, budget, deadline, get_time_ms());
+    if deadline_bandwidth(&task) + periodic.bandwidth() > MAX_DEADLINE_BANDWIDTH {
+        return Err(SysError::EBUSY);
+    }
+    task.inner_exclusive_access().sched.periodic = Some(periodic);
+    Ok(0)
+}
+
+///周期线程完成了当前周期的工作，阻塞到下一个周期开始。下一个周期已开始时立即返回，
+///已经过去的周期都算作错过截止时间。不是周期线程时返回 `EINVAL`
+pub fn sys_wait_next_period() -> SysResult {
+    let task = current_task().unwrap();
+    let now = get_time_ms();
+    let release = task
+        .inner_exclusive_access()
+        .sched
+        .periodic
+        .as_mut()
+        .ok_or(SysError::EINVAL)?
+        .next_period(now);
+    if release > now {
+        add_timer(release, task);
+        block_current_and_run_next();
+    }
+    Ok(0)
+}
+
+///把当前线程作为周期线程的统计写入 `stat`，不是周期线程时返回 `EINVAL`
+pub fn sys_periodic_stat(stat: *mut PeriodicStat) -> SysResult {
+    let periodic_stat = current_task()
+        .unwrap()
+        .inner_exclusive_access()
+        .sched
+        .periodic
+        .as_ref()
+        .ok_or(SysError::EINVAL)?
+        .stat;
+    UserPtr::new(current_user_token(), stat as *const PeriodicStat).write(periodic_stat)?;
+    Ok(0)
+}
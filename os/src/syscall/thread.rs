@@ -1,14 +1,18 @@
 use alloc::sync::Arc;
 
+use super::{SysError, SysResult};
 use crate::mm::KERNEL_SPACE;
-use crate::task::{add_task, current_task, current_user_process, ThreadControlBlock};
+use crate::task::{
+    add_task, block_current_and_run_next, current_task, current_user_process,
+    exit_current_thread_and_run_next, ThreadControlBlock,
+};
 use crate::trap::{trap_handler, TrapContext};
 
 ///创建线程
-pub fn sys_thread_create(entry: usize, arg:usize) -> isize {
+pub fn sys_thread_create(entry: usize, arg:usize) -> SysResult {
     let process = current_user_process();
     let ustack_base = current_task().unwrap().inner_exclusive_access().res.as_ref().unwrap().ustack_base();
-    let new_thread = Arc::new(ThreadControlBlock::new(process.clone(), ustack_base, true));
+    let new_thread = Arc::new(ThreadControlBlock::new(process.clone(), ustack_base, true)?);
     let new_thread_inner = new_thread.inner_exclusive_access();
     let new_thread_res = new_thread_inner.res.as_ref().unwrap();
     let new_thread_id = new_thread_res.tid;
@@ -27,43 +31,85 @@ pub fn sys_thread_create(entry: usize, arg:usize) -> isize {
         trap_handler as usize,
     );
     (*new_thread_trap_cx).x[10] = arg;
+    drop(new_thread_inner);
     add_task(new_thread.clone());
-    new_thread_id as isize
+    Ok(new_thread_id as isize)
 }
 
 ///获取线程标识符
-pub fn sys_gettid() -> isize {
-    current_task()
+pub fn sys_gettid() -> SysResult {
+    Ok(current_task()
     .unwrap()
     .inner_exclusive_access()
     .res
     .as_ref()
     .unwrap()
-    .tid as isize
+    .tid as isize)
 }
 
-///等待线程结束
-pub fn sys_waittid(tid: usize) -> i32 {
+///线程退出，主线程退出时整个进程退出
+pub fn sys_thread_exit(exit_code: i32) -> ! {
+    exit_current_thread_and_run_next(exit_code);
+    panic!("Unreachable in sys_thread_exit!");
+}
+
+///等待线程结束，线程仍在运行时阻塞调用线程
+pub fn sys_waittid(tid: usize) -> SysResult {
     let thread = current_task().unwrap();
-    let thread_inner = thread.inner_exclusive_access();
     let process = current_user_process();
-    let mut process_inner = process.inner_exclusive_access();
-    if tid == thread_inner.res.as_ref().unwrap().tid {
-        return -1;
+    if tid == thread.inner_exclusive_access().res.as_ref().unwrap().tid {
+        return Err(SysError::EDEADLK);
     }
-    let mut exit_code = None;
-    let waited_thread = process_inner.threads[tid].as_ref();
-    if let Some(waited_thread) = waited_thread {
-        if let Some(waited_exit_code) = waited_thread.inner_exclusive_access().exit_code {
-            exit_code = Some(waited_exit_code);
+    loop {
+        let mut process_inner = process.inner_exclusive_access();
+        //tid越界或线程已被回收
+        let waited_thread = process_inner
+            .threads
+            .get(tid)
+            .and_then(|t| t.as_ref())
+            .cloned()
+            .ok_or(SysError::ESRCH)?;
+        let mut waited_inner = waited_thread.inner_exclusive_access();
+        //分离线程不可等待，且同一线程只能被一个线程等待
+        if waited_inner.detached {
+            return Err(SysError::EINVAL);
+        }
+        if let Some(exit_code) = waited_inner.exit_code {
+            drop(waited_inner);
+            let waited_thread = process_inner.threads[tid].take();
+            drop(process_inner);
+            //回收线程资源时需要访问进程控制块
+            drop(waited_thread);
+            return Ok(exit_code as isize);
+        }
+        match waited_inner.joiner.as_ref() {
+            Some(joiner) if !Arc::ptr_eq(joiner, &thread) => return Err(SysError::EINVAL),
+            _ => waited_inner.joiner = Some(thread.clone()),
         }
-    } else {
-        return -1;
+        drop(waited_inner);
+        drop(process_inner);
+        block_current_and_run_next();
     }
-    if let Some(exit_code) = exit_code {
-        process_inner.threads[tid] = None;
-        exit_code
-    } else {
-        -2 
+}
+
+///将线程设为分离状态，其退出后资源由内核自动回收
+pub fn sys_thread_detach(tid: usize) -> SysResult {
+    let process = current_user_process();
+    let process_inner = process.inner_exclusive_access();
+    let thread = process_inner
+        .threads
+        .get(tid)
+        .and_then(|t| t.as_ref())
+        .cloned()
+        .ok_or(SysError::ESRCH)?;
+    drop(process_inner);
+    let mut thread_inner = thread.inner_exclusive_access();
+    if thread_inner.detached || thread_inner.joiner.is_some() {
+        return Err(SysError::EINVAL);
     }
-}
\ No newline at end of file
+    thread_inner.detached = true;
+    drop(thread_inner);
+    //线程可能已经退出，此后不会再有人回收它
+    process.reap_detached_threads();
+    Ok(0)
+}
@@ -3,6 +3,7 @@ use super::process::ProcessControlBlock;
 use crate::config::{KERNEL_STACK_SIZE, PAGE_SIZE, TRAMPOLINE, TRAP_CONTEXT, USER_STACK_SIZE};
 use crate::mm::{MapPermission, PhysPageNum, VirtAddr, KERNEL_SPACE};
 use crate::sync::UPSafeCell;
+use crate::syscall::SysResult;
 use alloc::vec::Vec;
 use alloc::sync::{Arc, Weak};
 use lazy_static::*;
@@ -74,16 +75,18 @@ pub struct KernelStack {
 }
 
 impl KernelStack {
-    ///Create a kernelstack from pid
-    pub fn new() -> Self {
+    ///Create a kernelstack, fails with `ENOMEM` if there are not enough free frames
+    pub fn new() -> SysResult<Self> {
         let kstack_id = KSTACK_ALLOCATOR.exclusive_access().alloc();
+        // the id is given back on failure when the stack is dropped
+        let kernel_stack = KernelStack { kstack_id };
         let (kernel_stack_bottom, kernel_stack_top) = kernel_stack_position(kstack_id);
         KERNEL_SPACE.exclusive_access().insert_framed_area(
             kernel_stack_bottom.into(),
             kernel_stack_top.into(),
             MapPermission::R | MapPermission::W,
-        );
-        KernelStack { kstack_id }
+        )?;
+        Ok(kernel_stack)
     }
     ///Get the value on the top of kernelstack
     pub fn get_top(&self) -> usize {
@@ -92,7 +95,7 @@ impl KernelStack {
     }
 }
 
-pub fn kstack_alloc() -> KernelStack {
+pub fn kstack_alloc() -> SysResult<KernelStack> {
     KernelStack::new()
 }
 
@@ -112,43 +115,57 @@ pub struct TaskUserRes {
     pub tid: usize, //线程标识符
     pub ustack_base: usize, //用户栈基址
     pub process: Weak<ProcessControlBlock>, //所属进程的弱引用
+    user_res_freed: bool, //Trap上下文与用户栈已回收，线程退出时先于线程标识符回收
 }
 
 impl TaskUserRes {
     ///新建一个线程的资源集合，参数中的布尔值防止重复分配
-    pub fn new(ustack_base: usize, process: Arc<ProcessControlBlock>, alloc_user_res: bool) -> Self {
+    pub fn new(
+        ustack_base: usize,
+        process: Arc<ProcessControlBlock>,
+        alloc_user_res: bool,
+    ) -> SysResult<Self> {
         let tid = process.inner_exclusive_access().alloc_tid();
-        let task_user_res = Self {
+        let mut task_user_res = Self {
             tid: tid,
             ustack_base: ustack_base,
             process: Arc::downgrade(&process),
+            user_res_freed: false,
         };
         if alloc_user_res {
-            task_user_res.alloc_user_res();
+            task_user_res.alloc_user_res()?;
         }
-        task_user_res
+        Ok(task_user_res)
     }
-    ///分配Trap上下文以及用户栈资源
-    pub fn alloc_user_res(&self) {
+    ///分配Trap上下文以及用户栈资源，内存不足时返回 `ENOMEM` 且不分配任何资源
+    pub fn alloc_user_res(&mut self) -> SysResult<()> {
         let process = self.process.upgrade().unwrap();
         let mut process_inner = process.inner_exclusive_access();
         let ustack_bottom = ustack_bottom_from_tid(self.ustack_base, self.tid);
         let ustack_top = ustack_bottom + USER_STACK_SIZE;
-        process_inner.memory_set.insert_framed_area(
+        process_inner.memory_set.insert_lazy_area(
             ustack_bottom.into(), 
             ustack_top.into(),
             MapPermission::R | MapPermission::U | MapPermission::W,
         );
         let trap_cx_bottom = trap_cx_bottom_from_tid(self.tid);
         let trap_cx_top = trap_cx_bottom + PAGE_SIZE;
-        process_inner.memory_set.insert_framed_area(
+        let result = process_inner.memory_set.insert_framed_area(
             trap_cx_bottom.into(),
              trap_cx_top.into(),
              MapPermission::R | MapPermission::W,
             );
+        if result.is_err() {
+            process_inner.memory_set.remove_area_with_start_vpn(ustack_bottom.into());
+        }
+        self.user_res_freed = result.is_err();
+        result
     }
-    ///回收Trap上下文以及用户栈资源
-    pub fn dealloc_user_res(&self) {
+    ///回收Trap上下文以及用户栈资源，已回收时什么也不做
+    pub fn dealloc_user_res(&mut self) {
+        if core::mem::replace(&mut self.user_res_freed, true) {
+            return;
+        }
         let process = self.process.upgrade().unwrap();
         let mut process_inner = process.inner_exclusive_access();
         let ustack_bottom = ustack_bottom_from_tid(self.ustack_base, self.tid);
@@ -181,6 +198,10 @@ impl TaskUserRes {
     pub fn ustack_base(&self) -> usize {
         self.ustack_base
     }
+    ///线程用户栈底地址
+    pub fn ustack_bottom(&self) -> usize {
+        ustack_bottom_from_tid(self.ustack_base, self.tid)
+    }
     ///线程用户栈顶地址
     pub fn ustack_top(&self) -> usize {
         ustack_bottom_from_tid(self.ustack_base, self.tid) + USER_STACK_SIZE
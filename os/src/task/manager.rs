@@ -1,43 +1,126 @@
 //!Implementation of [`TaskManager`]
 use super::process::ProcessControlBlock;
-use super::{TaskStatus, ThreadControlBlock};
+use super::scheduler::{EdfScheduler, RtScheduler, Scheduler, SchedulerImpl};
+use super::{current_task, TaskStatus, ThreadControlBlock};
 use crate::sync::UPSafeCell;
-use crate::timer::remove_timer;
+use crate::timer::{get_time_ms, remove_timer};
 use alloc::collections::btree_map::BTreeMap;
-use alloc::collections::VecDeque;
 use alloc::sync::Arc;
 use lazy_static::*;
 ///任务管理器结构
 pub struct TaskManager {
-    ready_queue: VecDeque<Arc<ThreadControlBlock>>,
+    ///普通线程的调度器
+    scheduler: SchedulerImpl,
+    ///实时线程的调度器，它的线程先于普通线程运行
+    rt: RtScheduler,
+    ///周期线程的调度器，它的线程先于实时线程运行
+    edf: EdfScheduler,
+    ///就绪的线程应先于当前线程运行，当前线程应尽快让出处理器
+    need_resched: bool,
 }
 
-/// A simple FIFO scheduler.
+/// The ready threads are kept by the scheduler chosen when building the kernel,
+/// the real-time ones by the real-time scheduler above it and the periodic ones
+/// by the EDF scheduler above both.
 impl TaskManager {
     ///创建一个任务管理器
     pub fn new() -> Self {
         Self {
-            ready_queue: VecDeque::new(),
+            scheduler: SchedulerImpl::default(),
+            rt: RtScheduler::default(),
+            edf: EdfScheduler::default(),
+            need_resched: false,
         }
     }
-    ///想就绪队列中添加线程
+    ///想就绪队列中添加线程，它应先于当前线程运行时抢占当前线程
     pub fn add(&mut self, task: Arc<ThreadControlBlock>) {
-        self.ready_queue.push_back(task);
+        let task_inner = task.inner_exclusive_access();
+        let periodic = task_inner.sched.periodic.is_some();
+        let realtime = task_inner.sched.is_realtime();
+        drop(task_inner);
+        if periodic {
+            self.edf.add(task);
+        } else if realtime {
+            self.rt.add(task);
+        } else {
+            self.scheduler.add(task);
+        }
+        if let Some(current) = current_task() {
+            if self.outranks(&current) {
+                self.need_resched = true;
+            }
+        }
+    }
+    ///是否有就绪的线程应先于 `current` 运行：截止时间更早的周期线程，或者
+    ///`current` 不是周期线程时优先级更高的实时线程
+    fn outranks(&self, current: &Arc<ThreadControlBlock>) -> bool {
+        let current_inner = current.inner_exclusive_access();
+        let earliest_deadline = self.edf.earliest_deadline();
+        match current_inner.sched.periodic.as_ref() {
+            Some(periodic) => earliest_deadline.is_some_and(|d| d < periodic.abs_deadline),
+            None => {
+                earliest_deadline.is_some()
+                    || self.rt.top_priority() > current_inner.sched.effective_rt_priority()
+            }
+        }
     }
-    ///弹出就绪队列中的第一个线程
+    ///取出下一个线程，先取周期线程，实时线程受到限制时先运行普通线程
     pub fn fetch(&mut self) -> Option<Arc<ThreadControlBlock>> {
-        self.ready_queue.pop_front()
+        self.need_resched = false;
+        if let Some(task) = self.edf.fetch(get_time_ms()) {
+            return Some(task);
+        }
+        if !self.rt.is_throttled() {
+            if let Some(task) = self.rt.fetch() {
+                return Some(task);
+            }
+        }
+        // 没有普通线程时，受到限制的实时线程也可以运行
+        self.scheduler.fetch().or_else(|| self.rt.fetch())
     }
     ///删除就绪队列中的指定线程
     pub fn remove(&mut self, task: Arc<ThreadControlBlock>) {
-        if let Some((id, _)) = self
-            .ready_queue
-            .iter()
-            .enumerate()
-            .find(|(_, t)| Arc::as_ptr(t) == Arc::as_ptr(&task))
-        {
-            self.ready_queue.remove(id);
+        self.scheduler.remove(&task);
+        self.rt.remove(&task);
+        self.edf.remove(&task);
+    }
+    ///`task` 运行时发生了一次时钟中断，返回它是否应让出处理器
+    pub fn on_tick(&mut self, task: &Arc<ThreadControlBlock>) -> bool {
+        self.edf.release(get_time_ms());
+        if task.inner_exclusive_access().sched.periodic.is_some() {
+            return self.edf.on_tick(task);
+        }
+        if self.rt.on_tick(task) {
+            return true;
+        }
+        if self.edf.earliest_deadline().is_some() {
+            let mut task_inner = task.inner_exclusive_access();
+            if task_inner.sched.is_realtime() {
+                task_inner.sched.preempted = true;
+            }
+            return true;
+        }
+        if task.inner_exclusive_access().sched.is_realtime() {
+            return false;
+        }
+        // 普通线程也要计入调度器的统计
+        let yield_normal = self.scheduler.on_tick(task);
+        yield_normal || self.rt.top_priority() > 0
+    }
+    ///`task` 即将阻塞
+    pub fn on_block(&mut self, task: &Arc<ThreadControlBlock>) {
+        self.scheduler.on_block(task);
+    }
+    ///当前线程是否应被抢占，被抢占的实时线程再次就绪时排在同优先级的队首
+    pub fn preempt(&mut self, current: &Arc<ThreadControlBlock>) -> bool {
+        if !core::mem::take(&mut self.need_resched) {
+            return false;
+        }
+        let mut current_inner = current.inner_exclusive_access();
+        if current_inner.sched.is_realtime() {
+            current_inner.sched.preempted = true;
         }
+        true
     }
 }
 
@@ -51,10 +134,49 @@ lazy_static! {
 pub fn add_task(task: Arc<ThreadControlBlock>) {
     TASK_MANAGER.exclusive_access().add(task);
 }
-///弹出就绪队列的第一个线程
+///取出调度器选中的下一个线程
 pub fn fetch_task() -> Option<Arc<ThreadControlBlock>> {
     TASK_MANAGER.exclusive_access().fetch()
 }
+///当前线程运行时发生了一次时钟中断，返回它是否应让出处理器
+pub fn tick_current_task() -> bool {
+    let task = current_task().unwrap();
+    TASK_MANAGER.exclusive_access().on_tick(&task)
+}
+///当前线程是否应被唤醒的周期线程或更高优先级的实时线程抢占
+pub fn preempt_current_task() -> bool {
+    let task = current_task().unwrap();
+    TASK_MANAGER.exclusive_access().preempt(&task)
+}
+///除 `except` 以外的周期线程的带宽之和，单位为千分之一
+pub fn deadline_bandwidth(except: &Arc<ThreadControlBlock>) -> usize {
+    let mut bandwidth = 0;
+    for process in PID2PCB.exclusive_access().values() {
+        let process_inner = process.inner_exclusive_access();
+        for thread in process_inner.threads.iter().flatten() {
+            if Arc::ptr_eq(thread, except) {
+                continue;
+            }
+            let thread_inner = thread.inner_exclusive_access();
+            if thread_inner.exit_code.is_none() {
+                if let Some(periodic) = thread_inner.sched.periodic.as_ref() {
+                    bandwidth += periodic.bandwidth();
+                }
+            }
+        }
+    }
+    bandwidth
+}
+///通知调度器 `task` 即将阻塞
+pub fn block_task(task: &Arc<ThreadControlBlock>) {
+    TASK_MANAGER.exclusive_access().on_block(task);
+}
+///就绪线程的调度参数改变后，按新的参数重新加入就绪队列
+pub fn requeue_task(task: &Arc<ThreadControlBlock>) {
+    let mut task_manager = TASK_MANAGER.exclusive_access();
+    task_manager.remove(task.clone());
+    task_manager.add(task.clone());
+}
 ///去除就绪队列和睡眠等待队列中的指定线程
 pub fn remove_task(task: Arc<ThreadControlBlock>) {
     TASK_MANAGER.exclusive_access().remove(task.clone());
@@ -87,6 +209,10 @@ pub fn remove_from_pid2process(pid: usize) {
 ///唤醒阻塞线程并加入就绪队列
 pub fn wakeup_task(task: Arc<ThreadControlBlock>) {
     let mut task_inner = task.inner_exclusive_access();
+    // 线程所在进程已退出，它可能仍留在某个等待队列中，此时不能再运行
+    if task_inner.res.is_none() || task_inner.exit_code.is_some() {
+        return;
+    }
     task_inner.task_status = TaskStatus::Ready;
     drop(task_inner);
     add_task(task);
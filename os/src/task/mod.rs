@@ -20,21 +20,32 @@ mod id;
 mod processor;
 mod switch;
 mod process;
+mod scheduler;
 mod thread;
 
-use crate::loader::get_app_data_by_name;
+use crate::fs::{open_file, OpenFlags};
 use crate::sbi::shutdown;
-use alloc::{sync::Arc, vec::Vec};
+use crate::sync::UPSafeCell;
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
 use id::TaskUserRes;
 use lazy_static::*;
-use manager::{remove_from_pid2process, remove_task};
+use manager::{block_task, remove_from_pid2process, remove_task, PID2PCB};
 pub use manager::{fetch_task, TaskManager};
 use process::ProcessControlBlock;
+use processor::defer_drop_exited_task;
 use switch::__switch;
 pub use thread::{ThreadControlBlock, TaskStatus};
 
 pub use context::TaskContext;
-pub use manager::{add_task, wakeup_task};
+pub use manager::{
+    add_task, deadline_bandwidth, preempt_current_task, requeue_task, tick_current_task,
+    wakeup_task,
+};
+pub use scheduler::{
+    Periodic, PeriodicStat, SchedPolicy, MAX_DEADLINE_BANDWIDTH, MAX_PERIOD, MAX_RT_PRIORITY,
+    MIN_PRIORITY,
+};
 pub use id::{pid_alloc, KernelStack, RecycleAllocator, PidHandle};
 pub use processor::{
     current_task, current_trap_cx, current_user_token, run_tasks, schedule, take_current_task,
@@ -63,7 +74,7 @@ pub fn suspend_current_and_run_next() {
 /// pid of usertests app in make run TEST=1
 pub const IDLE_PID: usize = 0;
 
-/// 退出当前线程并运行下一线程
+/// 退出当前线程所在的进程并运行下一线程，可由进程中任一线程调用
 pub fn exit_current_and_run_next(exit_code: i32) {
     // take from Processor
     let task = take_current_task().unwrap();
@@ -75,8 +86,17 @@ pub fn exit_current_and_run_next(exit_code: i32) {
     task_inner.res = None;
     drop(task_inner);
     drop(task);
+    exit_process(&process, exit_code, Some(tid));
+    drop(process);
+    // we do not have to save task context
+    let mut _unused = TaskContext::zero_init();
+    schedule(&mut _unused as *mut _);
+}
 
-    if tid == 0 {
+/// 退出进程，`running_tid` 为仍运行在自己内核栈上的当前线程，
+/// 其余线程不再运行，它们的资源立即被回收
+fn exit_process(process: &Arc<ProcessControlBlock>, exit_code: i32, running_tid: Option<usize>) {
+    {
         let pid = process.getpid();
         if pid == IDLE_PID {
             println!(
@@ -98,10 +118,23 @@ pub fn exit_current_and_run_next(exit_code: i32) {
 
         {
             let mut initproc_inner = INITPROC.inner_exclusive_access();
+            let mut has_zombie = false;
             for child in process_inner.children.iter() {
-                child.inner_exclusive_access().parent = Some(Arc::downgrade(&INITPROC));
+                let mut child_inner = child.inner_exclusive_access();
+                child_inner.parent = Some(Arc::downgrade(&INITPROC));
+                has_zombie |= child_inner.is_zombie;
                 initproc_inner.children.push(child.clone());
             }
+            // 移交给initproc的僵尸进程需要由它回收
+            if has_zombie {
+                initproc_inner.wakeup_child_exit_waiters();
+            }
+        }
+        // 本进程中等待子进程的线程不会再运行
+        process_inner.child_exit_waiters.clear();
+        // 唤醒在waitpid中等待的父进程
+        if let Some(parent) = process_inner.parent.as_ref().and_then(|p| p.upgrade()) {
+            parent.inner_exclusive_access().wakeup_child_exit_waiters();
         }
         let mut recycle_res = Vec::<TaskUserRes>::new();
         for thread in process_inner.threads.iter().filter(|t| t.is_some()) {
@@ -117,15 +150,129 @@ pub fn exit_current_and_run_next(exit_code: i32) {
 
         let mut process_inner = process.inner_exclusive_access();
         process_inner.children.clear();
+        // 关闭所有文件，管道的另一端由此得知本进程已退出
+        process_inner.fd_table.clear();
         
         process_inner.memory_set.recycle_data_pages();
 
-        while process_inner.threads.len() > 1 {
-            process_inner.threads.pop();
+        // 当前线程仍运行在自己的内核栈上，它的线程控制块随进程一起被父进程回收
+        for (i, thread) in process_inner.threads.iter_mut().enumerate() {
+            if Some(i) != running_tid {
+                *thread = None;
+            }
         }
     }
-    drop(process);
-    // we do not have to save task context
+}
+
+/// OOM killer杀死的进程的退出码
+pub const OOM_EXIT_CODE: i32 = -9;
+
+lazy_static! {
+    /// 被OOM killer选中、尚未退出的进程
+    static ref OOM_VICTIMS: UPSafeCell<Vec<Weak<ProcessControlBlock>>> =
+        unsafe { UPSafeCell::new(Vec::new()) };
+}
+
+/// 缺页处理找不到空闲物理页帧时调用，`current_rss` 为当前进程的常驻页数。
+/// 选出常驻页最多的进程作为牺牲者(initproc除外)，若是其他进程则立即回收
+/// 它的用户页并返回 true，调用者可以再次尝试分配；若是当前进程则返回 false。
+/// 牺牲者在下一次返回用户态前由 [`exit_oom_victims`] 杀死。
+///
+/// 调用者持有当前进程控制块的借用，因此这里不能访问当前进程
+pub fn oom_kill(current_rss: usize) -> bool {
+    let current = current_user_process();
+    let victim = PID2PCB
+        .exclusive_access()
+        .values()
+        .filter(|process| !Arc::ptr_eq(process, &current) && !Arc::ptr_eq(process, &INITPROC))
+        .map(|process| {
+            let rss = process.inner_exclusive_access().memory_set.resident_pages();
+            (rss, Arc::clone(process))
+        })
+        .max_by_key(|(rss, _)| *rss)
+        .filter(|(rss, _)| *rss > current_rss);
+    let (rss, victim, killed_other) = match victim {
+        Some((rss, victim)) => (rss, victim, true),
+        None => (current_rss, current, false),
+    };
+    println!(
+        "[kernel] Out of memory: killed process {} with {} resident pages.",
+        victim.getpid(),
+        rss
+    );
+    if killed_other {
+        victim
+            .inner_exclusive_access()
+            .memory_set
+            .recycle_user_pages();
+    }
+    OOM_VICTIMS.exclusive_access().push(Arc::downgrade(&victim));
+    killed_other
+}
+
+/// 杀死被OOM killer选中的进程，当前进程也被选中时不再返回
+pub fn exit_oom_victims() {
+    if OOM_VICTIMS.exclusive_access().is_empty() {
+        return;
+    }
+    let current = current_user_process();
+    let mut exit_current = false;
+    loop {
+        let victim = OOM_VICTIMS.exclusive_access().pop();
+        let victim = match victim {
+            Some(victim) => victim,
+            None => break,
+        };
+        // 牺牲者可能已经自行退出
+        let victim = match victim.upgrade() {
+            Some(victim) if !victim.inner_exclusive_access().is_zombie => victim,
+            _ => continue,
+        };
+        if Arc::ptr_eq(&victim, &current) {
+            exit_current = true;
+        } else {
+            exit_process(&victim, OOM_EXIT_CODE, None);
+        }
+    }
+    drop(current);
+    if exit_current {
+        exit_current_and_run_next(OOM_EXIT_CODE);
+    }
+}
+
+/// 仅退出当前线程并运行下一线程，主线程退出时整个进程退出
+pub fn exit_current_thread_and_run_next(exit_code: i32) {
+    let task = current_task().unwrap();
+    let tid = task.inner_exclusive_access().res.as_ref().unwrap().tid;
+    if tid == 0 {
+        drop(task);
+        exit_current_and_run_next(exit_code);
+        return;
+    }
+    drop(task);
+    let task = take_current_task().unwrap();
+    let mut task_inner = task.inner_exclusive_access();
+    task_inner.exit_code = Some(exit_code);
+    // 立即释放用户栈与Trap上下文，线程标识符保留到线程被回收为止
+    task_inner.res.as_mut().unwrap().dealloc_user_res();
+    let joiner = task_inner.joiner.take();
+    let detached = task_inner.detached;
+    drop(task_inner);
+    // 唤醒等待该线程结束的线程
+    if let Some(joiner) = joiner {
+        wakeup_task(joiner);
+    }
+    if detached {
+        // 分离线程立即离开进程的线程队列，线程控制块连同当前内核栈在切换到空闲控制流后回收
+        let process = task.process.upgrade().unwrap();
+        let slot = process.inner_exclusive_access().threads[tid].take();
+        drop(slot);
+        drop(process);
+        defer_drop_exited_task(task);
+    } else {
+        // 线程控制块仍由进程的线程队列持有，当前内核栈在被回收前保持有效
+        drop(task);
+    }
     let mut _unused = TaskContext::zero_init();
     schedule(&mut _unused as *mut _);
 }
@@ -137,13 +284,14 @@ pub fn block_current_and_run_next() {
     let task_cx_ptr = &mut thread_inner.task_cx as *mut TaskContext;
     thread_inner.task_status = TaskStatus::Blocked;
     drop(thread_inner);
+    block_task(&thread);
     schedule(task_cx_ptr);
 }
 
 lazy_static! {
     ///Globle process that init user shell
     pub static ref INITPROC: Arc<ProcessControlBlock> =ProcessControlBlock::new(
-        get_app_data_by_name("initproc").unwrap()
+        open_file("initproc", OpenFlags::RDONLY).unwrap().read_all().unwrap().as_slice()
     );
 }
 ///Add init process to the manager
@@ -1,12 +1,17 @@
 //!Implementation of [`ProcessControlBlock`]
 use super::manager::insert_into_pid2process;
-use super::{add_task, RecycleAllocator};
+use super::{add_task, wakeup_task, RecycleAllocator};
 use super::{pid_alloc, PidHandle};
+use crate::config::MAX_FD_NUM;
+use crate::fs::{File, Stdin, Stdout};
 use crate::mm::{MemorySet, KERNEL_SPACE};
 use crate::sync::{HoareMonitor, Mutex, Semaphore, UPSafeCell};
+use crate::syscall::{SysError, SysResult};
 use crate::trap::{trap_handler, TrapContext};
 use crate::task::ThreadControlBlock;
+use alloc::collections::VecDeque;
 use alloc::sync::{Arc, Weak};
+use alloc::vec;
 use alloc::vec::Vec;
 use core::cell::RefMut;
 
@@ -25,10 +30,12 @@ pub struct ProcessControlBlockInner {
     pub children: Vec<Arc<ProcessControlBlock>>, //子进程队列
     pub exit_code: i32, //进程退出码
     pub threads: Vec<Option<Arc<ThreadControlBlock>>>, //该进程下的线程队列
+    pub fd_table: Vec<Option<Arc<dyn File>>>, //文件描述符表
     pub mutex_list: Vec<Option<Arc<Mutex>>>, //互斥锁资源队列
     pub sem_list: Vec<Option<Arc<Semaphore>>>, //信号量资源队列
     pub monitor_list: Vec<Option<Arc<HoareMonitor>>>, //霍尔管程资源队列
     pub thread_res_allocator: RecycleAllocator, //线程资源分配器
+    pub child_exit_waiters: VecDeque<Arc<ThreadControlBlock>>, //等待子进程退出而阻塞的线程队列
 }
 
 impl ProcessControlBlockInner {
@@ -41,6 +48,26 @@ impl ProcessControlBlockInner {
         self.memory_set.token()
     }
 
+    ///分配编号最小的空闲文件描述符，超出上限时返回 `EMFILE`
+    pub fn alloc_fd(&mut self) -> SysResult<usize> {
+        if let Some(fd) = (0..self.fd_table.len()).find(|fd| self.fd_table[*fd].is_none()) {
+            Ok(fd)
+        } else if self.fd_table.len() < MAX_FD_NUM {
+            self.fd_table.push(None);
+            Ok(self.fd_table.len() - 1)
+        } else {
+            Err(SysError::EMFILE)
+        }
+    }
+
+    ///获取文件描述符对应的文件，无效时返回 `EBADF`
+    pub fn get_file(&self, fd: usize) -> SysResult<Arc<dyn File>> {
+        self.fd_table
+            .get(fd)
+            .and_then(|file| file.clone())
+            .ok_or(SysError::EBADF)
+    }
+
     pub fn alloc_tid(&mut self) -> usize {
         self.thread_res_allocator.alloc()
     } 
@@ -49,13 +76,36 @@ impl ProcessControlBlockInner {
         self.thread_res_allocator.dealloc(tid);
     }
 
+    ///进程中尚未被回收的线程数
     pub fn thread_count(&self) -> usize {
-        self.threads.len()
+        self.threads.iter().filter(|t| t.is_some()).count()
     }
 
     pub fn get_task(&self, tid:usize) -> Arc<ThreadControlBlock> {
         self.threads[tid].as_ref().unwrap().clone()
     } 
+
+    ///取出已退出的分离线程，调用者需在释放对进程控制块的借用后再丢弃它们
+    pub fn take_exited_detached_threads(&mut self) -> Vec<Arc<ThreadControlBlock>> {
+        let mut exited = Vec::new();
+        for thread in self.threads.iter_mut() {
+            let is_exited = thread.as_ref().map_or(false, |t| {
+                let thread_inner = t.inner_exclusive_access();
+                thread_inner.detached && thread_inner.exit_code.is_some()
+            });
+            if is_exited {
+                exited.push(thread.take().unwrap());
+            }
+        }
+        exited
+    }
+
+    ///唤醒所有等待子进程退出的线程
+    pub fn wakeup_child_exit_waiters(&mut self) {
+        while let Some(thread) = self.child_exit_waiters.pop_front() {
+            wakeup_task(thread);
+        }
+    }
 }
 
 impl ProcessControlBlock {
@@ -66,7 +116,7 @@ impl ProcessControlBlock {
     ///新建一个进程控制块
     pub fn new(elf_data: &[u8]) -> Arc<Self> {
          //需要注意原本的MemorySet::from_elf(elf_data)方法需要改进以适应当前需求
-        let (memory_set, ustack_base, entry_point) = MemorySet::from_elf(elf_data);
+        let (memory_set, ustack_base, entry_point) = MemorySet::from_elf(elf_data).unwrap();
         // alloc a pid and a kernel stack in kernel space
         let pid_handle = pid_alloc();
         let process = Arc::new(Self {
@@ -79,10 +129,19 @@ impl ProcessControlBlock {
                     children: Vec::new(),
                     exit_code: 0,
                     threads: Vec::new(),
+                    fd_table: vec![
+                        // 0 -> stdin
+                        Some(Arc::new(Stdin::new())),
+                        // 1 -> stdout
+                        Some(Arc::new(Stdout)),
+                        // 2 -> stderr
+                        Some(Arc::new(Stdout)),
+                    ],
                     mutex_list: Vec::new(),
                     sem_list: Vec::new(),
                     monitor_list: Vec::new(),
                     thread_res_allocator: RecycleAllocator::new(),
+                    child_exit_waiters: VecDeque::new(),
                 })
             },
         });
@@ -91,7 +150,7 @@ impl ProcessControlBlock {
             Arc::clone(&process), 
             ustack_base, 
             true
-        ));
+        ).unwrap());
         let main_thread_inner = main_thread.inner_exclusive_access();
         let trap_cx = main_thread_inner.get_trap_cx();
         let ustack_top = main_thread_inner.res.as_ref().unwrap().ustack_top();
@@ -111,15 +170,24 @@ impl ProcessControlBlock {
         insert_into_pid2process(process.getpid(), process.clone());
         process
     }
-    ///此方法可以指定进程将要执行的代码
-    pub fn exec(&self, elf_data: &[u8]) {
+    ///此方法可以指定进程将要执行的代码，已打开的文件描述符保持不变。
+    ///内存不足时返回 `ENOMEM`，原有的地址空间保持不变
+    pub fn exec(&self, elf_data: &[u8]) -> SysResult<()> {
         assert_eq!(self.inner_exclusive_access().thread_count(), 1);
-        let (memory_set, ustack_base, entry_point) = MemorySet::from_elf(elf_data);
-        self.inner_exclusive_access().memory_set = memory_set;
+        let (memory_set, ustack_base, entry_point) = MemorySet::from_elf(elf_data)?;
+        let old_memory_set =
+            core::mem::replace(&mut self.inner_exclusive_access().memory_set, memory_set);
         let task = self.inner_exclusive_access().get_task(0);
         let mut task_inner = task.inner_exclusive_access();
+        let old_ustack_base = task_inner.res.as_ref().unwrap().ustack_base;
         task_inner.res.as_mut().unwrap().ustack_base = ustack_base;
-        task_inner.res.as_mut().unwrap().alloc_user_res();
+        if let Err(err) = task_inner.res.as_mut().unwrap().alloc_user_res() {
+            // 恢复原有的地址空间，当前线程的Trap上下文仍在其中
+            task_inner.res.as_mut().unwrap().ustack_base = old_ustack_base;
+            self.inner_exclusive_access().memory_set = old_memory_set;
+            return Err(err);
+        }
+        drop(old_memory_set);
         task_inner.trap_cx_ppn = task_inner.res.as_mut().unwrap().trap_cx_ppn();
         let user_sp = task_inner.res.as_mut().unwrap().ustack_top();
         // initialize trap_cx
@@ -132,14 +200,17 @@ impl ProcessControlBlock {
         );
         *task_inner.get_trap_cx() = trap_cx;
         // **** release inner automatically
+        Ok(())
     }
-    ///创建进程的子进程
-    pub fn fork(self: &Arc<Self>) -> Arc<Self> {
+    ///创建进程的子进程，内存不足时返回 `ENOMEM`
+    pub fn fork(self: &Arc<Self>) -> SysResult<Arc<Self>> {
         // ---- access parent PCB exclusively
         let mut parent_inner = self.inner_exclusive_access();
         assert_eq!(parent_inner.thread_count(), 1);
-        let memory_set = MemorySet::from_existed_user(&parent_inner.memory_set);
+        let memory_set = MemorySet::from_existed_user(&mut parent_inner.memory_set)?;
         let pid_handle = pid_alloc();
+        // 子进程继承父进程打开的所有文件
+        let fd_table = parent_inner.fd_table.clone();
         let child_process = Arc::new(ProcessControlBlock {
             pid: pid_handle,
             inner: unsafe {
@@ -149,16 +220,16 @@ impl ProcessControlBlock {
                     parent: Some(Arc::downgrade(self)),
                     children: Vec::new(),
                     threads: Vec::new(),
+                    fd_table,
                     mutex_list: Vec::new(),
                     sem_list: Vec::new(),
                     monitor_list: Vec::new(),
                     thread_res_allocator: RecycleAllocator::new(),
+                    child_exit_waiters: VecDeque::new(),
                     exit_code: 0,
                 })
             },
         });
-        // add child
-        parent_inner.children.push(child_process.clone());
         let child_main_thread = Arc::new(
             ThreadControlBlock::new(
             child_process.clone(),
@@ -170,7 +241,9 @@ impl ProcessControlBlock {
             .unwrap()
             .ustack_base(),
             false
-        ));
+        )?);
+        // add child
+        parent_inner.children.push(child_process.clone());
         let mut child_process_inner = child_process.inner_exclusive_access();
         child_process_inner.threads.push(Some(Arc::clone(&child_main_thread)));
         drop(child_process_inner);
@@ -180,7 +253,13 @@ impl ProcessControlBlock {
         drop(child_main_thread_inner);
         add_task(child_main_thread);
         insert_into_pid2process(child_process.getpid(), child_process.clone());
-        child_process
+        Ok(child_process)
+    }
+    ///回收已退出的分离线程的线程标识符与内核栈
+    pub fn reap_detached_threads(&self) {
+        let exited = self.inner_exclusive_access().take_exited_detached_threads();
+        // 线程资源的回收需要再次访问进程控制块
+        drop(exited);
     }
     ///获取进程标识符
     pub fn getpid(&self) -> usize {
@@ -5,6 +5,7 @@ use super::thread::ThreadControlBlock;
 use super::{fetch_task, TaskStatus};
 use super::TaskContext;
 use crate::sync::UPSafeCell;
+use crate::timer::check_timer;
 use crate::trap::TrapContext;
 use alloc::sync::Arc;
 use lazy_static::*;
@@ -14,6 +15,8 @@ pub struct Processor {
     current: Option<Arc<ThreadControlBlock>>,
     ///The basic control flow of each core, helping to select and switch process
     idle_task_cx: TaskContext,
+    ///刚退出的分离线程，切换到空闲控制流后才能释放它的内核栈
+    exited: Option<Arc<ThreadControlBlock>>,
 }
 
 impl Processor {
@@ -22,6 +25,7 @@ impl Processor {
         Self {
             current: None,
             idle_task_cx: TaskContext::zero_init(),
+            exited: None,
         }
     }
     ///Get mutable reference to `idle_task_cx`
@@ -45,6 +49,9 @@ lazy_static! {
 ///Loop `fetch_task` to get the process that needs to run, and switch the process through `__switch`
 pub fn run_tasks() {
     loop {
+        // 已不在其内核栈上运行，可以回收刚退出的分离线程；回收时需要访问进程控制块
+        let exited = PROCESSOR.exclusive_access().exited.take();
+        drop(exited);
         let mut processor = PROCESSOR.exclusive_access();
         if let Some(task) = fetch_task() {
             let idle_task_cx_ptr = processor.get_idle_task_cx_ptr();
@@ -60,6 +67,11 @@ pub fn run_tasks() {
             unsafe {
                 __switch(idle_task_cx_ptr, next_task_cx_ptr);
             }
+        } else {
+            // 内核中不开中断，没有线程就绪时时钟中断不会到来，由空闲控制流唤醒到期的线程；
+            // 唤醒时需要访问处理器管理结构
+            drop(processor);
+            check_timer();
         }
     }
 }
@@ -67,6 +79,10 @@ pub fn run_tasks() {
 pub fn take_current_task() -> Option<Arc<ThreadControlBlock>> {
     PROCESSOR.exclusive_access().take_current()
 }
+///已退出的线程仍运行在自己的内核栈上，交给空闲控制流回收
+pub fn defer_drop_exited_task(task: Arc<ThreadControlBlock>) {
+    PROCESSOR.exclusive_access().exited = Some(task);
+}
 ///克隆一份当前运行线程的线程控制块返回
 pub fn current_task() -> Option<Arc<ThreadControlBlock>> {
     PROCESSOR.exclusive_access().current()
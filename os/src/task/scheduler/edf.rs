@@ -0,0 +1,167 @@
+//! Earliest deadline first scheduling of periodic threads
+use crate::task::ThreadControlBlock;
+use crate::timer::TICK_MS;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+///周期线程的带宽（预算与周期之比）之和的上限，单位为千分之一，
+///剩下的时间留给实时线程与普通线程
+pub const MAX_DEADLINE_BANDWIDTH: usize = 950;
+///周期的上限，单位为毫秒，更长的周期在计算带宽与时间时可能溢出
+pub const MAX_PERIOD: usize = 60 * 60 * 1000;
+
+///周期线程的统计，由 `periodic_stat` 返回
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct PeriodicStat {
+    ///完成的周期数
+    pub jobs: usize,
+    ///错过截止时间的周期数
+    pub deadline_misses: usize,
+    ///用完预算后被限制的次数
+    pub budget_overruns: usize,
+}
+
+///周期线程的参数与状态，时间的单位均为毫秒
+pub struct Periodic {
+    pub period: usize,       //周期
+    pub budget: usize,       //每个周期中最多运行的时间
+    pub deadline: usize,     //每个周期的截止时间，相对于周期的开始
+    pub release: usize,      //当前周期的开始
+    pub abs_deadline: usize, //当前周期的截止时间
+    pub budget_used: usize,  //当前周期已用的预算，按时钟中断计
+    pub stat: PeriodicStat,  //统计
+}
+
+impl Periodic {
+    ///从 `now` 开始第一个周期
+    pub fn new(period: usize, budget: usize, deadline: usize, now: usize) -> Self {
+        Self {
+            period,
+            budget,
+            deadline,
+            release: now,
+            abs_deadline: now + deadline,
+            budget_used: 0,
+            stat: PeriodicStat::default(),
+        }
+    }
+    ///带宽，单位为千分之一
+    pub fn bandwidth(&self) -> usize {
+        self.budget * 1000 / self.period
+    }
+    ///当前周期的工作已完成，进入下一个周期，已经过去的周期都算作错过截止时间。
+    ///返回下一个周期的开始
+    pub fn next_period(&mut self, now: usize) -> usize {
+        self.stat.jobs += 1;
+        if now > self.abs_deadline {
+            self.stat.deadline_misses += 1;
+        }
+        self.release += self.period;
+        while self.release + self.deadline <= now {
+            self.release += self.period;
+            self.stat.deadline_misses += 1;
+        }
+        self.abs_deadline = self.release + self.deadline;
+        self.budget_used = 0;
+        self.release
+    }
+    ///用完预算后 `now` 所在的周期开始，补充预算。当前的工作必然错过截止时间，
+    ///其间完整经过的周期也都算作错过截止时间
+    fn replenish(&mut self, now: usize) {
+        let periods = (now - self.release) / self.period;
+        self.stat.deadline_misses += periods;
+        self.release += periods * self.period;
+        self.abs_deadline = self.release + self.deadline;
+        self.budget_used = 0;
+    }
+    fn is_throttled(&self) -> bool {
+        self.budget_used >= self.budget
+    }
+}
+
+///最早截止时间优先：总是运行截止时间最早的周期线程。周期线程在实时线程与普通线程
+///之前运行，但每个周期中最多运行预算那么长的时间，用完后受到限制直到下一个周期。
+#[derive(Default)]
+pub struct EdfScheduler {
+    ready: Vec<Arc<ThreadControlBlock>>,
+    ///用完预算的线程
+    throttled: Vec<Arc<ThreadControlBlock>>,
+}
+
+fn abs_deadline(task: &Arc<ThreadControlBlock>) -> usize {
+    task.inner_exclusive_access()
+        .sched
+        .periodic
+        .as_ref()
+        .unwrap()
+        .abs_deadline
+}
+
+impl EdfScheduler {
+    ///加入一个就绪的周期线程，用完预算的线程等到下一个周期才能运行
+    pub fn add(&mut self, task: Arc<ThreadControlBlock>) {
+        let throttled = task
+            .inner_exclusive_access()
+            .sched
+            .periodic
+            .as_ref()
+            .unwrap()
+            .is_throttled();
+        if throttled {
+            self.throttled.push(task);
+        } else {
+            self.ready.push(task);
+        }
+    }
+    ///取出截止时间最早的线程
+    pub fn fetch(&mut self, now: usize) -> Option<Arc<ThreadControlBlock>> {
+        self.release(now);
+        let (idx, _) = self
+            .ready
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, task)| abs_deadline(task))?;
+        Some(self.ready.remove(idx))
+    }
+    ///删除指定的线程，不在调度器中时什么也不做
+    pub fn remove(&mut self, task: &Arc<ThreadControlBlock>) {
+        self.ready.retain(|t| !Arc::ptr_eq(t, task));
+        self.throttled.retain(|t| !Arc::ptr_eq(t, task));
+    }
+    ///下一个周期已开始的受限线程补充预算后重新就绪
+    pub fn release(&mut self, now: usize) {
+        let mut i = 0;
+        while i < self.throttled.len() {
+            let mut task_inner = self.throttled[i].inner_exclusive_access();
+            let periodic = task_inner.sched.periodic.as_mut().unwrap();
+            if periodic.release + periodic.period <= now {
+                periodic.replenish(now);
+                drop(task_inner);
+                let task = self.throttled.swap_remove(i);
+                self.ready.push(task);
+            } else {
+                i += 1;
+            }
+        }
+    }
+    ///就绪线程中最早的截止时间
+    pub fn earliest_deadline(&self) -> Option<usize> {
+        self.ready.iter().map(abs_deadline).min()
+    }
+    ///周期线程 `task` 运行时发生了一次时钟中断，扣除预算。返回它是否用完了预算，
+    ///或有截止时间更早的线程就绪
+    pub fn on_tick(&mut self, task: &Arc<ThreadControlBlock>) -> bool {
+        let mut task_inner = task.inner_exclusive_access();
+        let periodic = task_inner.sched.periodic.as_mut().unwrap();
+        periodic.budget_used += TICK_MS;
+        if periodic.is_throttled() {
+            periodic.stat.budget_overruns += 1;
+            return true;
+        }
+        let deadline = periodic.abs_deadline;
+        drop(task_inner);
+        self.earliest_deadline()
+            .is_some_and(|earliest| earliest < deadline)
+    }
+}
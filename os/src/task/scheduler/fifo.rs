@@ -0,0 +1,26 @@
+//! First in, first out scheduler
+use super::Scheduler;
+use crate::task::ThreadControlBlock;
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+
+///先来先服务，每个时钟中断轮转一次，忽略优先级
+#[derive(Default)]
+pub struct FifoScheduler {
+    ready_queue: VecDeque<Arc<ThreadControlBlock>>,
+}
+
+impl Scheduler for FifoScheduler {
+    fn add(&mut self, task: Arc<ThreadControlBlock>) {
+        self.ready_queue.push_back(task);
+    }
+    fn fetch(&mut self) -> Option<Arc<ThreadControlBlock>> {
+        self.ready_queue.pop_front()
+    }
+    fn remove(&mut self, task: &Arc<ThreadControlBlock>) {
+        self.ready_queue.retain(|t| !Arc::ptr_eq(t, task));
+    }
+    fn on_tick(&mut self, _task: &Arc<ThreadControlBlock>) -> bool {
+        true
+    }
+}
@@ -0,0 +1,86 @@
+//! Multi-level feedback queue scheduler
+use super::Scheduler;
+use crate::task::ThreadControlBlock;
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+
+///就绪队列的级数，第 0 级最先运行
+const LEVELS: usize = 3;
+///每隔这么多个时钟中断，所有线程回到第 0 级，防止低级的线程饿死
+const BOOST_TICKS: usize = 100;
+
+///第 `level` 级的时间片，单位为时钟中断，级别越低时间片越长
+fn time_slice(level: usize) -> usize {
+    1 << level
+}
+
+///多级反馈队列：总是运行最高一级队列中的线程，同级之间轮转。用完整个时间片的线程
+///降一级，阻塞的线程升一级，交互式的线程因此比计算密集的线程先运行。
+#[derive(Default)]
+pub struct MlfqScheduler {
+    queues: [VecDeque<Arc<ThreadControlBlock>>; LEVELS],
+    ///时钟中断的次数
+    ticks: usize,
+    ///提升的次数，线程的提升次数落后时回到第 0 级，包括不在就绪队列中的线程
+    epoch: usize,
+}
+
+impl MlfqScheduler {
+    ///`task` 所在的级别，上次提升后还没有回到第 0 级时先回到第 0 级
+    fn level(&self, task: &Arc<ThreadControlBlock>) -> usize {
+        let mut task_inner = task.inner_exclusive_access();
+        let sched = &mut task_inner.sched;
+        if sched.epoch != self.epoch {
+            sched.epoch = self.epoch;
+            sched.level = 0;
+            sched.slice_used = 0;
+        }
+        sched.level
+    }
+    ///所有线程回到第 0 级
+    fn boost(&mut self) {
+        self.epoch += 1;
+        let (top, lower) = self.queues.split_first_mut().unwrap();
+        for queue in lower {
+            top.append(queue);
+        }
+    }
+}
+
+impl Scheduler for MlfqScheduler {
+    fn add(&mut self, task: Arc<ThreadControlBlock>) {
+        let level = self.level(&task);
+        self.queues[level].push_back(task);
+    }
+    fn fetch(&mut self) -> Option<Arc<ThreadControlBlock>> {
+        self.queues.iter_mut().find_map(|queue| queue.pop_front())
+    }
+    fn remove(&mut self, task: &Arc<ThreadControlBlock>) {
+        for queue in self.queues.iter_mut() {
+            queue.retain(|t| !Arc::ptr_eq(t, task));
+        }
+    }
+    fn on_tick(&mut self, task: &Arc<ThreadControlBlock>) -> bool {
+        self.ticks += 1;
+        if self.ticks % BOOST_TICKS == 0 {
+            self.boost();
+        }
+        let level = self.level(task);
+        let mut task_inner = task.inner_exclusive_access();
+        let sched = &mut task_inner.sched;
+        sched.slice_used += 1;
+        if sched.slice_used >= time_slice(level) {
+            sched.slice_used = 0;
+            sched.level = (level + 1).min(LEVELS - 1);
+            return true;
+        }
+        // a thread of a higher level is ready
+        self.queues[..level].iter().any(|queue| !queue.is_empty())
+    }
+    fn on_block(&mut self, task: &Arc<ThreadControlBlock>) {
+        let level = self.level(task);
+        let mut task_inner = task.inner_exclusive_access();
+        task_inner.sched.level = level.saturating_sub(1);
+        task_inner.sched.slice_used = 0;
+    }
+}
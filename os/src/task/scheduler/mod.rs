@@ -0,0 +1,127 @@
+//! Scheduling policies, which pick the next thread to run
+//!
+//! [`TaskManager`](super::TaskManager) keeps the ready threads in a
+//! [`Scheduler`], and the one used is chosen when building the kernel:
+//! stride scheduling by default, FIFO with the `sched-fifo` feature and a
+//! multi-level feedback queue with the `sched-mlfq` feature. The real-time
+//! threads of [`RtScheduler`] always run before them, and the periodic threads
+//! of [`EdfScheduler`] before the real-time ones.
+mod edf;
+#[cfg_attr(not(feature = "sched-fifo"), allow(dead_code))]
+mod fifo;
+#[cfg_attr(not(feature = "sched-mlfq"), allow(dead_code))]
+mod mlfq;
+mod rt;
+#[cfg_attr(any(feature = "sched-fifo", feature = "sched-mlfq"), allow(dead_code))]
+mod stride;
+
+use super::ThreadControlBlock;
+use alloc::sync::Arc;
+pub use edf::{EdfScheduler, Periodic, PeriodicStat, MAX_DEADLINE_BANDWIDTH, MAX_PERIOD};
+pub use rt::{RtScheduler, MAX_RT_PRIORITY};
+
+#[cfg(all(feature = "sched-fifo", feature = "sched-mlfq"))]
+compile_error!("the features sched-fifo and sched-mlfq can not be enabled together");
+
+#[cfg(feature = "sched-fifo")]
+///构建内核时选择的调度策略
+pub type SchedulerImpl = fifo::FifoScheduler;
+#[cfg(feature = "sched-mlfq")]
+///构建内核时选择的调度策略
+pub type SchedulerImpl = mlfq::MlfqScheduler;
+#[cfg(not(any(feature = "sched-fifo", feature = "sched-mlfq")))]
+///构建内核时选择的调度策略
+pub type SchedulerImpl = stride::StrideScheduler;
+
+///线程的默认优先级
+pub const DEFAULT_PRIORITY: usize = 16;
+///线程可设置的最低优先级
+pub const MIN_PRIORITY: usize = 2;
+
+///线程的调度类别，与 POSIX 的取值一致
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum SchedPolicy {
+    Normal = 0,     //普通线程，由构建内核时选择的调度策略调度
+    Fifo = 1,       //实时线程，运行到阻塞、让出或被更高优先级的线程抢占
+    RoundRobin = 2, //实时线程，同优先级的线程之间按时间片轮转
+}
+
+impl SchedPolicy {
+    ///由系统调用的参数得到调度类别
+    pub fn from_raw(policy: usize) -> Option<Self> {
+        match policy {
+            0 => Some(Self::Normal),
+            1 => Some(Self::Fifo),
+            2 => Some(Self::RoundRobin),
+            _ => None,
+        }
+    }
+    ///是否为实时调度类别
+    pub fn is_realtime(&self) -> bool {
+        *self != Self::Normal
+    }
+}
+
+///线程的调度参数，由调度器维护
+pub struct SchedInfo {
+    pub priority: usize,              //优先级，越大分到的处理器时间越多
+    pub pass: usize,                  //步幅调度中已走过的行程
+    pub level: usize,                 //多级反馈队列中所在的级别
+    pub slice_used: usize,            //多级反馈队列中当前时间片已用的时钟中断数
+    pub epoch: usize,                 //多级反馈队列中最近一次回到第 0 级时的提升次数
+    pub policy: SchedPolicy,          //调度类别
+    pub rt_priority: usize,           //实时优先级，普通线程为 0
+    pub rr_slice_used: usize,         //SCHED_RR 中当前时间片已用的时钟中断数
+    pub preempted: bool,              //实时线程被抢占，再次就绪时排在同优先级的队首
+    pub periodic: Option<Periodic>,   //周期线程的参数与状态，其他线程为 None
+    pub inherited_priority: usize,    //从等待其持有的互斥锁的线程继承的优先级，没有时为 0
+    pub inherited_rt_priority: usize, //从等待其持有的互斥锁的线程继承的实时优先级，没有时为 0
+}
+
+impl SchedInfo {
+    ///考虑优先级继承后的优先级
+    pub fn effective_priority(&self) -> usize {
+        self.priority.max(self.inherited_priority)
+    }
+    ///考虑优先级继承后的实时优先级，普通线程继承了实时优先级时按 SCHED_FIFO 调度
+    pub fn effective_rt_priority(&self) -> usize {
+        self.rt_priority.max(self.inherited_rt_priority)
+    }
+    ///是否按实时线程调度
+    pub fn is_realtime(&self) -> bool {
+        self.policy.is_realtime() || self.inherited_rt_priority > 0
+    }
+}
+
+impl Default for SchedInfo {
+    fn default() -> Self {
+        Self {
+            priority: DEFAULT_PRIORITY,
+            pass: 0,
+            level: 0,
+            slice_used: 0,
+            epoch: 0,
+            policy: SchedPolicy::Normal,
+            rt_priority: 0,
+            rr_slice_used: 0,
+            preempted: false,
+            periodic: None,
+            inherited_priority: 0,
+            inherited_rt_priority: 0,
+        }
+    }
+}
+
+///调度器，管理就绪线程
+pub trait Scheduler: Default {
+    ///加入一个就绪线程
+    fn add(&mut self, task: Arc<ThreadControlBlock>);
+    ///取出下一个要运行的线程
+    fn fetch(&mut self) -> Option<Arc<ThreadControlBlock>>;
+    ///删除指定的就绪线程，不在就绪队列中时什么也不做
+    fn remove(&mut self, task: &Arc<ThreadControlBlock>);
+    ///`task` 运行时发生了一次时钟中断，返回它是否应让出处理器
+    fn on_tick(&mut self, task: &Arc<ThreadControlBlock>) -> bool;
+    ///`task` 即将阻塞
+    fn on_block(&mut self, _task: &Arc<ThreadControlBlock>) {}
+}
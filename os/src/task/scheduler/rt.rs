@@ -0,0 +1,100 @@
+//! Real-time scheduling classes, SCHED_FIFO and SCHED_RR
+use super::SchedPolicy;
+use crate::task::ThreadControlBlock;
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::sync::Arc;
+
+///实时优先级的最大值，最小值为 1
+pub const MAX_RT_PRIORITY: usize = 99;
+///SCHED_RR 的时间片，单位为时钟中断
+const RR_TIME_SLICE: usize = 10;
+///实时线程的运行时间以这么多个时钟中断为一个周期统计
+const RT_PERIOD_TICKS: usize = 100;
+///每个周期中实时线程最多运行这么多个时钟中断，剩下的时间留给普通线程，
+///失控的实时线程因此不会让 initproc 等普通线程永远饿死
+const RT_RUNTIME_TICKS: usize = 95;
+
+///实时调度：总是运行实时优先级最高的线程，同优先级之间 SCHED_FIFO 的线程
+///先来先服务，SCHED_RR 的线程按时间片轮转。实时线程在普通线程之前运行，
+///但在一个周期中用完运行时间后受到限制，直到周期结束。
+#[derive(Default)]
+pub struct RtScheduler {
+    ///就绪的实时线程，按实时优先级分队
+    queues: BTreeMap<usize, VecDeque<Arc<ThreadControlBlock>>>,
+    ///当前周期中的时钟中断数
+    period_ticks: usize,
+    ///当前周期中实时线程运行的时钟中断数
+    runtime_ticks: usize,
+}
+
+impl RtScheduler {
+    ///加入一个就绪的实时线程，被抢占的线程排在同优先级的队首
+    pub fn add(&mut self, task: Arc<ThreadControlBlock>) {
+        let mut task_inner = task.inner_exclusive_access();
+        let priority = task_inner.sched.effective_rt_priority();
+        let preempted = core::mem::take(&mut task_inner.sched.preempted);
+        drop(task_inner);
+        let queue = self.queues.entry(priority).or_default();
+        if preempted {
+            queue.push_front(task);
+        } else {
+            queue.push_back(task);
+        }
+    }
+    ///取出优先级最高的实时线程，不论是否受到限制
+    pub fn fetch(&mut self) -> Option<Arc<ThreadControlBlock>> {
+        let mut entry = self.queues.last_entry()?;
+        let task = entry.get_mut().pop_front();
+        if entry.get().is_empty() {
+            entry.remove();
+        }
+        task
+    }
+    ///删除指定的就绪线程，不在就绪队列中时什么也不做
+    pub fn remove(&mut self, task: &Arc<ThreadControlBlock>) {
+        self.queues.retain(|_, queue| {
+            queue.retain(|t| !Arc::ptr_eq(t, task));
+            !queue.is_empty()
+        });
+    }
+    ///实时线程是否已用完当前周期的运行时间
+    pub fn is_throttled(&self) -> bool {
+        self.runtime_ticks >= RT_RUNTIME_TICKS
+    }
+    ///可以运行的实时线程的最高优先级，没有时为 0
+    pub fn top_priority(&self) -> usize {
+        if self.is_throttled() {
+            return 0;
+        }
+        self.queues
+            .last_key_value()
+            .map_or(0, |(priority, _)| *priority)
+    }
+    ///`task` 运行时发生了一次时钟中断，返回实时线程是否应让出处理器，普通线程
+    ///总是返回 false
+    pub fn on_tick(&mut self, task: &Arc<ThreadControlBlock>) -> bool {
+        self.period_ticks += 1;
+        if self.period_ticks >= RT_PERIOD_TICKS {
+            self.period_ticks = 0;
+            self.runtime_ticks = 0;
+        }
+        let mut task_inner = task.inner_exclusive_access();
+        let sched = &mut task_inner.sched;
+        if !sched.is_realtime() {
+            return false;
+        }
+        self.runtime_ticks += 1;
+        if self.is_throttled() || self.top_priority() > sched.effective_rt_priority() {
+            sched.preempted = true;
+            return true;
+        }
+        if sched.policy == SchedPolicy::RoundRobin {
+            sched.rr_slice_used += 1;
+            if sched.rr_slice_used >= RR_TIME_SLICE {
+                sched.rr_slice_used = 0;
+                return true;
+            }
+        }
+        false
+    }
+}
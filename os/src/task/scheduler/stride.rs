@@ -0,0 +1,63 @@
+//! Stride scheduler
+use super::Scheduler;
+use crate::task::ThreadControlBlock;
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+
+///优先级为 1 时的步幅
+const BIG_STRIDE: usize = 1 << 32;
+
+///`a` 的行程是否小于 `b`，行程会回绕，两者之差不超过 `BIG_STRIDE`
+fn pass_before(a: usize, b: usize) -> bool {
+    (a.wrapping_sub(b) as isize) < 0
+}
+
+///步幅调度：每次选行程最小的线程运行，并让它前进 `BIG_STRIDE / 优先级`，
+///因此各线程分到的处理器时间与优先级成正比。行程相同时先来先服务。
+#[derive(Default)]
+pub struct StrideScheduler {
+    ready_queue: VecDeque<Arc<ThreadControlBlock>>,
+    ///最近一次被选中的线程出发时的行程，新加入或睡眠后醒来的线程从这里出发，
+    ///不会因为落后太多而长时间独占处理器
+    min_pass: usize,
+}
+
+impl Scheduler for StrideScheduler {
+    fn add(&mut self, task: Arc<ThreadControlBlock>) {
+        let mut task_inner = task.inner_exclusive_access();
+        if pass_before(task_inner.sched.pass, self.min_pass) {
+            task_inner.sched.pass = self.min_pass;
+        }
+        drop(task_inner);
+        self.ready_queue.push_back(task);
+    }
+    fn fetch(&mut self) -> Option<Arc<ThreadControlBlock>> {
+        let mut next = 0;
+        let mut next_pass = self
+            .ready_queue
+            .front()?
+            .inner_exclusive_access()
+            .sched
+            .pass;
+        for (i, task) in self.ready_queue.iter().enumerate().skip(1) {
+            let pass = task.inner_exclusive_access().sched.pass;
+            if pass_before(pass, next_pass) {
+                next = i;
+                next_pass = pass;
+            }
+        }
+        let task = self.ready_queue.remove(next).unwrap();
+        let mut task_inner = task.inner_exclusive_access();
+        let stride = (BIG_STRIDE / task_inner.sched.effective_priority()).max(1);
+        task_inner.sched.pass = next_pass.wrapping_add(stride);
+        drop(task_inner);
+        self.min_pass = next_pass;
+        Some(task)
+    }
+    fn remove(&mut self, task: &Arc<ThreadControlBlock>) {
+        self.ready_queue.retain(|t| !Arc::ptr_eq(t, task));
+    }
+    fn on_tick(&mut self, _task: &Arc<ThreadControlBlock>) -> bool {
+        true
+    }
+}
@@ -1,12 +1,15 @@
 //!Implementation of [`TaskControlBlock`]
 use super::id::{kstack_alloc, TaskUserRes};
 use super::process::ProcessControlBlock;
+use super::scheduler::SchedInfo;
 use super::TaskContext;
 use super::KernelStack;
 use crate::mm::PhysPageNum;
-use crate::sync::UPSafeCell;
+use crate::sync::{Mutex, UPSafeCell};
+use crate::syscall::SysResult;
 use crate::trap::TrapContext;
 use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
 use core::cell::RefMut;
 
 ///线程控制块
@@ -25,32 +28,42 @@ pub struct ThreadControlBlockInner {
     pub task_cx: TaskContext, //任务上下文
     pub task_status: TaskStatus, //线程状态
     pub exit_code: Option<i32>, //退出码
+    pub joiner: Option<Arc<ThreadControlBlock>>, //阻塞等待该线程结束的线程
+    pub detached: bool, //是否为分离线程，分离线程退出后由内核自动回收
+    pub sched: SchedInfo, //调度参数
+    pub blocked_on: Option<Weak<Mutex>>, //正在等待的互斥锁
+    pub held_mutexes: Vec<Weak<Mutex>>, //持有的互斥锁
 }
 
 impl ThreadControlBlock {
-    ///创建一个线程控制块
+    ///创建一个线程控制块，内存不足时返回 `ENOMEM`
     pub fn new(
         process: Arc<ProcessControlBlock>,
         ustack_base: usize,
         alloc_user_res: bool        
-        ) -> Self {
-            let task_user_res = TaskUserRes::new(ustack_base, process.clone(), alloc_user_res);
+        ) -> SysResult<Self> {
+            let task_user_res = TaskUserRes::new(ustack_base, process.clone(), alloc_user_res)?;
             let trap_cx_ppn = task_user_res.trap_cx_ppn();
-            let kernel_stack = kstack_alloc();
+            let kernel_stack = kstack_alloc()?;
             let task_cx = TaskContext::goto_trap_return(kernel_stack.get_top());
             let thread_inner = ThreadControlBlockInner {
                 res: Some(task_user_res),
                 trap_cx_ppn: trap_cx_ppn,
                 task_cx: task_cx,
                 task_status: TaskStatus::Ready,
-                exit_code: None
+                exit_code: None,
+                joiner: None,
+                detached: false,
+                sched: SchedInfo::default(),
+                blocked_on: None,
+                held_mutexes: Vec::new(),
             };
             let thread = ThreadControlBlock {
                 process: Arc::downgrade(&process),
                 kernel_stack: kernel_stack,
                 inner: unsafe { UPSafeCell::new(thread_inner) }
             };
-            thread
+            Ok(thread)
         }
     ///获取线程控制块inner成员变量的可变引用
     pub fn inner_exclusive_access(&self) -> RefMut<'_, ThreadControlBlockInner> {
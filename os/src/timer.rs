@@ -13,6 +13,8 @@ use lazy_static::*;
 
 const TICKS_PER_SEC: usize = 100;
 const MSEC_PER_SEC: usize = 1000;
+/// milliseconds between two timer interrupts
+pub const TICK_MS: usize = MSEC_PER_SEC / TICKS_PER_SEC;
 ///get current time
 pub fn get_time() -> usize {
     time::read()
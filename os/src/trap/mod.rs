@@ -14,9 +14,12 @@
 mod context;
 
 use crate::config::TRAMPOLINE;
-use crate::syscall::syscall;
+use crate::mm::{MapPermission, VirtAddr};
+use crate::syscall::{syscall, SysError};
 use crate::task::{
-    current_trap_cx, current_trap_cx_va, current_user_token, exit_current_and_run_next, suspend_current_and_run_next
+    current_trap_cx, current_trap_cx_va, current_user_process, current_user_token,
+    exit_current_and_run_next, exit_oom_victims, preempt_current_task, suspend_current_and_run_next,
+    tick_current_task,
 };
 use crate::timer::{check_timer, set_next_trigger};
 use core::arch::{asm, global_asm};
@@ -67,6 +70,14 @@ pub fn trap_handler() -> ! {
             cx = current_trap_cx();
             cx.x[10] = result as usize;
         }
+        Trap::Exception(Exception::StorePageFault)
+        | Trap::Exception(Exception::LoadPageFault)
+        | Trap::Exception(Exception::InstructionPageFault)
+            if handle_page_fault(scause.cause(), stval) =>
+        {
+            // the page has been mapped, retry the faulting instruction, or
+            // the process is out of memory and killed in `trap_return`
+        }
         Trap::Exception(Exception::StoreFault)
         | Trap::Exception(Exception::StorePageFault)
         | Trap::Exception(Exception::InstructionFault)
@@ -74,7 +85,7 @@ pub fn trap_handler() -> ! {
         | Trap::Exception(Exception::LoadFault)
         | Trap::Exception(Exception::LoadPageFault) => {
             println!(
-                "[kernel] {:?} in application, bad addr = {:#x}, bad instruction = {:#x}, kernel killed it.",
+                "[kernel] Segmentation fault: {:?} in application, bad addr = {:#x}, bad instruction = {:#x}, kernel killed it.",
                 scause.cause(),
                 stval,
                 current_trap_cx().sepc,
@@ -90,7 +101,9 @@ pub fn trap_handler() -> ! {
         Trap::Interrupt(Interrupt::SupervisorTimer) => {
             set_next_trigger();
             check_timer();
-            suspend_current_and_run_next();
+            if tick_current_task() {
+                suspend_current_and_run_next();
+            }
         }
         _ => {
             panic!(
@@ -100,14 +113,36 @@ pub fn trap_handler() -> ! {
             );
         }
     }
+    // a real-time thread of a higher priority has been woken up
+    if preempt_current_task() {
+        suspend_current_and_run_next();
+    }
     trap_return();
 }
 
+/// Map a lazy page or copy a copy-on-write page at `va`, returns false on a
+/// segmentation fault
+fn handle_page_fault(cause: Trap, va: usize) -> bool {
+    let access = match cause {
+        Trap::Exception(Exception::StorePageFault) => MapPermission::W,
+        Trap::Exception(Exception::InstructionPageFault) => MapPermission::X,
+        _ => MapPermission::R,
+    };
+    let process = current_user_process();
+    let mut inner = process.inner_exclusive_access();
+    inner
+        .memory_set
+        .handle_page_fault(VirtAddr::from(va).floor(), access)
+        != Err(SysError::EFAULT)
+}
+
 #[no_mangle]
 /// set the new addr of __restore asm function in TRAMPOLINE page,
 /// set the reg a0 = trap_cx_ptr, reg a1 = phy addr of usr page table,
 /// finally, jump to new addr of __restore asm function
 pub fn trap_return() -> ! {
+    // the processes picked by the OOM killer never go back to U mode
+    exit_oom_victims();
     set_user_trap_entry();
     let trap_cx_ptr = current_trap_cx_va();
     let user_satp = current_user_token();
@@ -0,0 +1,42 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exec, fork, waitpid, write, SysError};
+
+/// kernel .text, mapped without the U flag
+const KERNEL_ADDR: usize = 0x8020_0000;
+/// never mapped in user space
+const UNMAPPED_ADDR: usize = 0x1000_0000;
+/// the kernel trap context page, mapped without the U flag
+const TRAP_CONTEXT_ADDR: usize = usize::MAX - 2 * 4096 + 1;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    for addr in [KERNEL_ADDR, UNMAPPED_ADDR, TRAP_CONTEXT_ADDR] {
+        let buf = unsafe { core::slice::from_raw_parts(addr as *const u8, 16) };
+        assert_eq!(write(1, buf), SysError::EFAULT.as_ret());
+    }
+    let pid = fork();
+    if pid == 0 {
+        exec("exit\0");
+        unreachable!();
+    }
+    // .text is mapped without the W flag, so the exit code cannot be stored there
+    let text_ptr = main as usize as *mut i32;
+    assert_eq!(
+        waitpid(pid as usize, unsafe { &mut *text_ptr }),
+        SysError::EFAULT.as_ret()
+    );
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    let path = unsafe {
+        core::str::from_utf8_unchecked(core::slice::from_raw_parts(KERNEL_ADDR as *const u8, 1))
+    };
+    assert_eq!(exec(path), SysError::EFAULT.as_ret());
+    assert_eq!(write(42, b"hello"), SysError::EBADF.as_ret());
+    println!("bad_address passed!");
+    0
+}
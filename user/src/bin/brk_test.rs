@@ -0,0 +1,86 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use user_lib::{brk, exit, fork, sbrk, waitpid, SysError};
+
+const PAGE_SIZE: usize = 4096;
+
+/// Run `f(arg)` in a child and return its exit code
+fn run_child(f: fn(usize), arg: usize) -> i32 {
+    let pid = fork();
+    if pid == 0 {
+        f(arg);
+        exit(0);
+    }
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    exit_code
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // far more than the initial heap of user_lib used to be
+    let mut v: Vec<usize> = Vec::new();
+    for i in 0..64 * 1024 {
+        v.push(i);
+    }
+    assert!(v.iter().enumerate().all(|(i, &x)| i == x));
+    let mut history: Vec<String> = Vec::new();
+    for i in 0..256 {
+        history.push(alloc::format!("entry {}", i));
+    }
+    assert_eq!(history[255], "entry 255");
+    drop(v);
+    drop(history);
+    // grow the heap by hand, the new memory is zeroed
+    let start = sbrk(0);
+    assert!(start > 0);
+    let start = start as usize;
+    assert_eq!(sbrk(2 * PAGE_SIZE as isize), start as isize);
+    assert_eq!(sbrk(0), (start + 2 * PAGE_SIZE) as isize);
+    let heap = unsafe { core::slice::from_raw_parts_mut(start as *mut u8, 2 * PAGE_SIZE) };
+    assert!(heap.iter().all(|&byte| byte == 0));
+    heap.fill(5);
+    // children share the heap copy-on-write
+    assert_eq!(
+        run_child(
+            |start| {
+                let heap =
+                    unsafe { core::slice::from_raw_parts_mut(start as *mut u8, 2 * PAGE_SIZE) };
+                assert!(heap.iter().all(|&byte| byte == 5));
+                heap.fill(6);
+            },
+            start
+        ),
+        0
+    );
+    assert!(heap.iter().all(|&byte| byte == 5));
+    // shrinking takes the pages away
+    assert_eq!(brk(start + PAGE_SIZE), 0);
+    assert_eq!(sbrk(0), (start + PAGE_SIZE) as isize);
+    assert_eq!(
+        run_child(
+            |addr| unsafe {
+                (addr as *const u8).read_volatile();
+            },
+            start + PAGE_SIZE
+        ),
+        -2
+    );
+    assert_eq!(heap[PAGE_SIZE - 1], 5);
+    // growing again gives zeroed pages
+    assert_eq!(sbrk(PAGE_SIZE as isize), (start + PAGE_SIZE) as isize);
+    assert_eq!(heap[PAGE_SIZE], 0);
+    // the break never goes below the end of the ELF image or out of user space
+    assert_eq!(sbrk(-(1 << 30)), SysError::ENOMEM.as_ret());
+    assert_eq!(sbrk(1 << 40), SysError::ENOMEM.as_ret());
+    assert_eq!(brk(start), 0);
+    println!("brk_test passed!");
+    0
+}
@@ -0,0 +1,70 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, exit, fork, pipe, read, waitpid, write};
+
+const PAGE_SIZE: usize = 4096;
+const PAGES: usize = 4;
+
+static mut DATA: [u8; PAGE_SIZE * PAGES] = [1; PAGE_SIZE * PAGES];
+
+fn data() -> &'static mut [u8; PAGE_SIZE * PAGES] {
+    unsafe { &mut *core::ptr::addr_of_mut!(DATA) }
+}
+
+fn check_page(page: usize, value: u8) {
+    assert!(data()[page * PAGE_SIZE..(page + 1) * PAGE_SIZE]
+        .iter()
+        .all(|&byte| byte == value));
+}
+
+fn wait_child(pid: isize) {
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let on_stack = [7u8; 64];
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    let pid = fork();
+    if pid == 0 {
+        // the child sees the data of the parent, and writes its own copy
+        check_page(0, 1);
+        data()[..PAGE_SIZE].fill(2);
+        check_page(0, 2);
+        assert!(on_stack.iter().all(|&byte| byte == 7));
+        // the kernel writes to a shared page on behalf of the child
+        close(pipe_fd[1]);
+        assert_eq!(read(pipe_fd[0], &mut data()[PAGE_SIZE..PAGE_SIZE + 5]), 5);
+        assert_eq!(&data()[PAGE_SIZE..PAGE_SIZE + 5], b"hello");
+        // a grandchild shares the pages copied by the child
+        let pid = fork();
+        if pid == 0 {
+            check_page(0, 2);
+            data()[..PAGE_SIZE].fill(3);
+            exit(0);
+        }
+        wait_child(pid);
+        check_page(0, 2);
+        exit(0);
+    }
+    close(pipe_fd[0]);
+    assert_eq!(write(pipe_fd[1], b"hello"), 5);
+    close(pipe_fd[1]);
+    wait_child(pid);
+    // nothing written by the children is visible here
+    for page in 0..PAGES {
+        check_page(page, 1);
+    }
+    // the only owner left writes in place
+    data()[PAGE_SIZE * 3..].fill(4);
+    check_page(3, 4);
+    println!("cow_test passed!");
+    0
+}
@@ -0,0 +1,103 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    get_time, periodic_stat, sched_setscheduler, set_periodic, thread_create, thread_exit,
+    wait_next_period, waittid, PeriodicStat, SysError, SCHED_NORMAL,
+};
+
+const PERIOD_MS: usize = 50;
+const PERIODS: usize = 10;
+/// a thread spinning through its periods without waiting
+const HOG_PERIOD_MS: usize = 100;
+const HOG_BUDGET_MS: usize = 20;
+const HOG_MS: isize = 350;
+/// periods shorter than a tick, several of them pass while the hog is throttled
+const FAST_PERIOD_MS: usize = 5;
+const FAST_MS: isize = 200;
+
+fn stat() -> PeriodicStat {
+    let mut stat = PeriodicStat::default();
+    assert_eq!(periodic_stat(&mut stat), 0);
+    stat
+}
+
+/// the periods start at fixed times, however long each loop takes
+fn test_no_drift() {
+    // more than a tick of budget for 2ms of work
+    assert_eq!(set_periodic(PERIOD_MS, 30, PERIOD_MS), 0);
+    let start = get_time();
+    for _ in 0..PERIODS {
+        let work_end = get_time() + 2;
+        while get_time() < work_end {}
+        assert_eq!(wait_next_period(), 0);
+    }
+    let elapsed = get_time() - start;
+    let stat = stat();
+    println!(
+        "{} periods of {}ms in {}ms: {:?}",
+        PERIODS, PERIOD_MS, elapsed, stat
+    );
+    let expected = (PERIODS * PERIOD_MS) as isize;
+    assert!(elapsed >= expected - 1 && elapsed < expected + 20);
+    assert_eq!(stat.jobs, PERIODS);
+    assert_eq!(stat.deadline_misses, 0);
+    assert_eq!(stat.budget_overruns, 0);
+    assert_eq!(sched_setscheduler(SCHED_NORMAL, 0), 0);
+    assert_eq!(wait_next_period(), SysError::EINVAL.as_ret());
+}
+
+fn hog(_arg: usize) -> ! {
+    assert_eq!(set_periodic(HOG_PERIOD_MS, HOG_BUDGET_MS, HOG_PERIOD_MS), 0);
+    let end = get_time() + HOG_MS;
+    while get_time() < end {}
+    let stat = stat();
+    println!("hog: {:?}", stat);
+    // throttled once in each period, and late for each of them
+    assert!(stat.budget_overruns >= 3);
+    assert!(stat.deadline_misses >= 2);
+    thread_exit(0)
+}
+
+fn fast_hog(_arg: usize) -> ! {
+    assert_eq!(set_periodic(FAST_PERIOD_MS, 1, FAST_PERIOD_MS), 0);
+    let end = get_time() + FAST_MS;
+    while get_time() < end {}
+    let stat = stat();
+    println!("fast hog: {:?}", stat);
+    // it catches up with the current period each time it is released, every
+    // period it spent throttled is missed
+    let periods = FAST_MS as usize / FAST_PERIOD_MS;
+    assert!(stat.deadline_misses >= periods * 3 / 4);
+    thread_exit(0)
+}
+
+/// a periodic thread running past its budget waits for the next period
+fn test_budget() {
+    for entry in [hog as usize, fast_hog as usize] {
+        let tid = thread_create(entry, 0) as usize;
+        assert_eq!(waittid(tid), 0);
+    }
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    assert_eq!(set_periodic(10, 0, 10), SysError::EINVAL.as_ret());
+    assert_eq!(set_periodic(10, 5, 20), SysError::EINVAL.as_ret());
+    assert_eq!(set_periodic(10, 8, 5), SysError::EINVAL.as_ret());
+    // the bandwidth of such a period does not fit in a usize
+    let huge = usize::MAX / 100;
+    assert_eq!(set_periodic(huge, huge, huge), SysError::EINVAL.as_ret());
+    // more than the whole CPU
+    assert_eq!(set_periodic(10, 10, 10), SysError::EBUSY.as_ret());
+    assert_eq!(wait_next_period(), SysError::EINVAL.as_ret());
+    let mut stat = PeriodicStat::default();
+    assert_eq!(periodic_stat(&mut stat), SysError::EINVAL.as_ret());
+    test_no_drift();
+    test_budget();
+    println!("edf_test passed!");
+    0
+}
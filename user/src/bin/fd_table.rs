@@ -0,0 +1,48 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, dup, dup2, exec, fork, read, waitpid, write, SysError};
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // stdin is not writable and stdout is not readable
+    assert_eq!(write(0, b"x"), SysError::EBADF.as_ret());
+    let mut buf = [0u8; 1];
+    assert_eq!(read(1, &mut buf), SysError::EBADF.as_ret());
+    // stderr goes to the console as well
+    assert_eq!(write(2, b"fd_table: stderr\n"), 17);
+    // dup takes the lowest free descriptor
+    let fd = dup(1);
+    assert_eq!(fd, 3);
+    assert_eq!(write(fd as usize, b"fd_table: dup\n"), 14);
+    assert_eq!(close(fd as usize), 0);
+    assert_eq!(close(fd as usize), SysError::EBADF.as_ret());
+    assert_eq!(write(fd as usize, b"x"), SysError::EBADF.as_ret());
+    assert_eq!(dup(42), SysError::EBADF.as_ret());
+    // dup2 to a descriptor beyond the end of the table
+    assert_eq!(dup2(1, 10), 10);
+    assert_eq!(write(10, b"fd_table: dup2\n"), 15);
+    assert_eq!(dup2(10, 10), 10);
+    // the freed slot 3 is reused before growing the table
+    assert_eq!(dup(0), 3);
+    assert_eq!(close(3), 0);
+    // children inherit the descriptors, closing them does not affect the parent
+    let pid = fork();
+    if pid == 0 {
+        assert_eq!(write(10, b"fd_table: inherited\n"), 20);
+        assert_eq!(close(10), 0);
+        assert_eq!(close(1), 0);
+        // descriptors survive exec, 1 is closed so nothing is printed
+        exec("hello_world\0");
+        unreachable!();
+    }
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(write(10, b"fd_table: still open\n"), 21);
+    assert_eq!(close(10), 0);
+    println!("fd_table passed!");
+    0
+}
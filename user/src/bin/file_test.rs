@@ -0,0 +1,106 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    close, fstat, lseek, open, pipe, read, unlink, write, OpenFlags, Stat, StatMode, SysError,
+    SEEK_CUR, SEEK_END, SEEK_SET,
+};
+
+const FILE: &str = "file_test_tmp\0";
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // a file left over from an earlier run
+    unlink(FILE);
+    assert_eq!(open(FILE, OpenFlags::RDONLY), SysError::ENOENT.as_ret());
+    // create, write and read back
+    let fd = open(FILE, OpenFlags::CREATE | OpenFlags::RDWR);
+    assert!(fd > 2);
+    let fd = fd as usize;
+    assert_eq!(write(fd, b"hello, easy-fs"), 14);
+    let mut buf = [0u8; 32];
+    assert_eq!(read(fd, &mut buf), 0);
+    assert_eq!(lseek(fd, 0, SEEK_SET), 0);
+    assert_eq!(read(fd, &mut buf), 14);
+    assert_eq!(&buf[..14], b"hello, easy-fs");
+    // seek around, but never before the start
+    assert_eq!(lseek(fd, -7, SEEK_END), 7);
+    assert_eq!(read(fd, &mut buf[..4]), 4);
+    assert_eq!(&buf[..4], b"easy");
+    assert_eq!(lseek(fd, -4, SEEK_CUR), 7);
+    assert_eq!(write(fd, b"EASY"), 4);
+    assert_eq!(lseek(fd, -1, SEEK_SET), SysError::EINVAL.as_ret());
+    assert_eq!(lseek(fd, 0, 3), SysError::EINVAL.as_ret());
+    let mut st = Stat::new();
+    assert_eq!(fstat(fd, &mut st), 0);
+    assert_eq!(st.mode, StatMode::FILE);
+    assert_eq!(st.size, 14);
+    assert_eq!(st.nlink, 1);
+    assert_eq!(close(fd), 0);
+    // a read only file can not be written
+    let fd = open(FILE, OpenFlags::RDONLY) as usize;
+    assert_eq!(write(fd, b"x"), SysError::EBADF.as_ret());
+    assert_eq!(read(fd, &mut buf), 14);
+    assert_eq!(&buf[..14], b"hello, EASY-fs");
+    assert_eq!(close(fd), 0);
+    // appends always go to the end
+    let fd = open(FILE, OpenFlags::WRONLY | OpenFlags::APPEND) as usize;
+    assert_eq!(lseek(fd, 0, SEEK_SET), 0);
+    assert_eq!(write(fd, b"!"), 1);
+    assert_eq!(fstat(fd, &mut st), 0);
+    assert_eq!(st.size, 15);
+    assert_eq!(read(fd, &mut buf), SysError::EBADF.as_ret());
+    assert_eq!(close(fd), 0);
+    // truncate
+    let fd = open(FILE, OpenFlags::RDWR | OpenFlags::TRUNC) as usize;
+    assert_eq!(fstat(fd, &mut st), 0);
+    assert_eq!(st.size, 0);
+    assert_eq!(write(fd, b"unlinked"), 8);
+    // an unlinked file stays readable until it is closed
+    assert_eq!(unlink(FILE), 0);
+    assert_eq!(unlink(FILE), SysError::ENOENT.as_ret());
+    assert_eq!(open(FILE, OpenFlags::RDONLY), SysError::ENOENT.as_ret());
+    assert_eq!(fstat(fd, &mut st), 0);
+    assert_eq!(st.nlink, 0);
+    assert_eq!(lseek(fd, 0, SEEK_SET), 0);
+    assert_eq!(read(fd, &mut buf), 8);
+    assert_eq!(&buf[..8], b"unlinked");
+    // easy-fs files are far smaller than 4 GiB, seeking there leaves the offset alone
+    assert_eq!(lseek(fd, 1 << 32, SEEK_SET), SysError::EINVAL.as_ret());
+    assert_eq!(lseek(fd, isize::MAX, SEEK_CUR), SysError::EINVAL.as_ret());
+    assert_eq!(write(fd, b"!"), 1);
+    assert_eq!(fstat(fd, &mut st), 0);
+    assert_eq!(st.size, 9);
+    assert_eq!(close(fd), 0);
+    // console and pipes can not seek
+    assert_eq!(lseek(1, 0, SEEK_SET), SysError::ESPIPE.as_ret());
+    assert_eq!(fstat(1, &mut st), 0);
+    assert_eq!(st.mode, StatMode::CHR);
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    assert_eq!(lseek(pipe_fd[0], 0, SEEK_SET), SysError::ESPIPE.as_ret());
+    assert_eq!(write(pipe_fd[1], b"abc"), 3);
+    assert_eq!(fstat(pipe_fd[0], &mut st), 0);
+    assert_eq!(st.mode, StatMode::FIFO);
+    assert_eq!(st.size, 3);
+    assert_eq!(close(pipe_fd[0]), 0);
+    assert_eq!(close(pipe_fd[1]), 0);
+    // bad names and flags
+    assert_eq!(
+        open(
+            "a_file_name_longer_than_27_bytes\0",
+            OpenFlags::CREATE | OpenFlags::WRONLY
+        ),
+        SysError::ENAMETOOLONG.as_ret()
+    );
+    assert_eq!(
+        open(FILE, OpenFlags::WRONLY | OpenFlags::RDWR),
+        SysError::EINVAL.as_ret()
+    );
+    assert_eq!(fstat(42, &mut st), SysError::EBADF.as_ret());
+    println!("file_test passed!");
+    0
+}
@@ -0,0 +1,60 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, exit, fork, mmap, munmap, pipe, read, waitpid, MmapProt};
+
+const PAGE_SIZE: usize = 4096;
+/// 48 MiB, every page touched
+const PAGES: usize = 48 * 256;
+/// each child keeps its own record of every page, which together take much
+/// more than the 2 MiB the kernel heap starts with
+const CHILDREN: usize = 8;
+
+fn page(addr: usize, i: usize) -> &'static mut usize {
+    unsafe { &mut *((addr + i * PAGE_SIZE) as *mut usize) }
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let addr = mmap(0, PAGES * PAGE_SIZE, MmapProt::READ | MmapProt::WRITE);
+    assert!(addr > 0);
+    let addr = addr as usize;
+    for i in 0..PAGES {
+        *page(addr, i) = i;
+    }
+    // the children are all alive at once, until the write end is closed
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    let mut pids = [0isize; CHILDREN];
+    for (child, pid) in pids.iter_mut().enumerate() {
+        *pid = fork();
+        if *pid == 0 {
+            close(pipe_fd[1]);
+            let mut byte = [0u8; 1];
+            assert_eq!(read(pipe_fd[0], &mut byte), 0);
+            for i in (0..PAGES).step_by(CHILDREN) {
+                assert_eq!(*page(addr, i), i);
+            }
+            *page(addr, child) = usize::MAX;
+            exit(0);
+        }
+        assert!(*pid > 0);
+    }
+    close(pipe_fd[0]);
+    close(pipe_fd[1]);
+    for pid in pids {
+        let mut exit_code = 0;
+        assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+        assert_eq!(exit_code, 0);
+    }
+    // the children wrote to their own copies
+    for i in 0..CHILDREN {
+        assert_eq!(*page(addr, i), i);
+    }
+    assert_eq!(munmap(addr, PAGES * PAGE_SIZE), 0);
+    println!("heap_grow_test passed!");
+    0
+}
@@ -4,7 +4,7 @@
 #[macro_use]
 extern crate user_lib;
 
-use user_lib::{exec, fork, wait, yield_};
+use user_lib::{exec, fork, wait, yield_, SysError};
 
 #[no_mangle]
 fn main() -> i32 {
@@ -14,7 +14,7 @@ fn main() -> i32 {
         loop {
             let mut exit_code: i32 = 0;
             let pid = wait(&mut exit_code);
-            if pid == -1 {
+            if pid == SysError::ECHILD.as_ret() {
                 yield_();
                 continue;
             }
@@ -0,0 +1,45 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, get_time, sleep, waitpid};
+
+/// CPU hogs running while the parent sleeps and wakes up again and again
+const HOGS: usize = 3;
+const HOG_MS: isize = 1000;
+const ROUNDS: usize = 20;
+const SLEEP_MS: usize = 10;
+/// an interactive thread gets the CPU soon after it wakes up
+const MAX_LATENCY_MS: isize = 100;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut pids = [0isize; HOGS];
+    for pid in pids.iter_mut() {
+        *pid = fork();
+        if *pid == 0 {
+            let end = get_time() + HOG_MS;
+            while get_time() < end {}
+            exit(0);
+        }
+        assert!(*pid > 0);
+    }
+    let mut max_latency = 0;
+    for _ in 0..ROUNDS {
+        let start = get_time();
+        sleep(SLEEP_MS);
+        let latency = get_time() - start - SLEEP_MS as isize;
+        max_latency = max_latency.max(latency);
+    }
+    println!("max wakeup latency with {} hogs: {}ms", HOGS, max_latency);
+    assert!(max_latency < MAX_LATENCY_MS);
+    for pid in pids {
+        let mut exit_code = 0;
+        assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+        assert_eq!(exit_code, 0);
+    }
+    println!("interactive_test passed!");
+    0
+}
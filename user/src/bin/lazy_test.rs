@@ -0,0 +1,86 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, exit, fork, pipe, read, waitpid, write};
+
+const PAGE_SIZE: usize = 4096;
+const PAGES: usize = 64;
+
+/// far more than this test touches, only the touched pages get frames
+static mut BIG: [u8; PAGE_SIZE * PAGES] = [0; PAGE_SIZE * PAGES];
+/// loaded from the ELF on first touch
+static INIT: [u32; 4] = [0xdead_beef, 1, 2, 3];
+
+fn big() -> &'static mut [u8; PAGE_SIZE * PAGES] {
+    unsafe { &mut *core::ptr::addr_of_mut!(BIG) }
+}
+
+/// Run `f` in a child and return its exit code
+fn run_child(f: fn()) -> i32 {
+    let pid = fork();
+    if pid == 0 {
+        f();
+        exit(0);
+    }
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    exit_code
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // untouched pages read as zero, and keep what is written
+    for page in (0..PAGES).step_by(7) {
+        assert_eq!(big()[page * PAGE_SIZE + 5], 0);
+        big()[page * PAGE_SIZE + 5] = page as u8;
+    }
+    for page in (0..PAGES).step_by(7) {
+        assert_eq!(big()[page * PAGE_SIZE + 5], page as u8);
+    }
+    assert_eq!(INIT, [0xdead_beef, 1, 2, 3]);
+    // the kernel touches a lazy page on behalf of a syscall
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    assert_eq!(write(pipe_fd[1], b"lazy"), 4);
+    let last = (PAGES - 1) * PAGE_SIZE;
+    assert_eq!(read(pipe_fd[0], &mut big()[last..last + 4]), 4);
+    assert_eq!(&big()[last..last + 4], b"lazy");
+    assert_eq!(write(pipe_fd[1], &big()[PAGE_SIZE * 2..PAGE_SIZE * 2 + 8]), 8);
+    close(pipe_fd[0]);
+    close(pipe_fd[1]);
+    // the stack gets its pages as it grows
+    assert_eq!(
+        run_child(|| {
+            let mut buf = [0u8; PAGE_SIZE + PAGE_SIZE / 2];
+            let buf = core::hint::black_box(&mut buf);
+            buf[0] = 1;
+            buf[buf.len() - 1] = 2;
+            assert_eq!(buf[0] as usize + buf[buf.len() - 1] as usize, 3);
+        }),
+        0
+    );
+    // a segmentation fault outside every area, and on a permission violation
+    assert_eq!(
+        run_child(|| unsafe {
+            (0x1000_0000 as *mut u8).write_volatile(1);
+        }),
+        -2
+    );
+    assert_eq!(
+        run_child(|| unsafe {
+            (main as usize as *mut u8).write_volatile(1);
+        }),
+        -2
+    );
+    assert_eq!(
+        run_child(|| unsafe {
+            (core::ptr::addr_of!(INIT) as *mut u32).write_volatile(1);
+        }),
+        -2
+    );
+    println!("lazy_test passed!");
+    0
+}
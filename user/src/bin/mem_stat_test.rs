@@ -0,0 +1,119 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use user_lib::{
+    close, exec, fork, mem_stat, mmap, munmap, pipe, thread_create, thread_exit, waitpid, waittid,
+    write, yield_, MemStat, MmapProt, SysError,
+};
+
+/// fork-heavy and thread-heavy tests whose frames must all come back
+const TESTS: [&str; 2] = ["forktree\0", "thread_join\0"];
+const THREADS: usize = 8;
+/// a buffer sized by user space, larger than the kernel heap grows by at once
+const LARGE_WRITE: usize = 1 << 20;
+
+static STARTED: AtomicUsize = AtomicUsize::new(0);
+static RELEASED: AtomicBool = AtomicBool::new(false);
+
+fn stat() -> MemStat {
+    let mut stat = MemStat::default();
+    assert_eq!(mem_stat(&mut stat), 0);
+    stat
+}
+
+/// Frames not held by any process. The kernel heap and the page tables of
+/// the kernel space keep the frames they grew by, the heap ones are counted.
+fn available_frames() -> usize {
+    let stat = stat();
+    stat.free_frames + stat.heap_frames
+}
+
+fn run(test: &str) {
+    let pid = fork();
+    if pid == 0 {
+        exec(test);
+        panic!("unreachable!");
+    }
+    assert!(pid > 0);
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+}
+
+fn worker(_arg: usize) -> ! {
+    // the stack of this thread is in use, keep it until `main` has seen it
+    STARTED.fetch_add(1, Ordering::Relaxed);
+    while !RELEASED.load(Ordering::Relaxed) {
+        yield_();
+    }
+    thread_exit(0)
+}
+
+/// each thread adds its own stack to the stack pages of the process
+fn run_threads() {
+    let stack_pages = stat().stack_pages;
+    STARTED.store(0, Ordering::Relaxed);
+    RELEASED.store(false, Ordering::Relaxed);
+    let mut tids = [0usize; THREADS];
+    for tid in tids.iter_mut() {
+        *tid = thread_create(worker as usize, 0) as usize;
+    }
+    while STARTED.load(Ordering::Relaxed) < THREADS {
+        yield_();
+    }
+    assert!(stat().stack_pages >= stack_pages + THREADS);
+    RELEASED.store(true, Ordering::Relaxed);
+    for tid in tids {
+        assert_eq!(waittid(tid), 0);
+    }
+    assert_eq!(stat().stack_pages, stack_pages);
+}
+
+/// the kernel copies the whole buffer before it finds the pipe closed, the
+/// copy must not stay in the kernel heap
+fn large_write() {
+    let addr = mmap(0, LARGE_WRITE, MmapProt::READ | MmapProt::WRITE);
+    assert!(addr > 0);
+    let buf = unsafe { core::slice::from_raw_parts(addr as *const u8, LARGE_WRITE) };
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    assert_eq!(close(pipe_fd[0]), 0);
+    let heap_frames = stat().heap_frames;
+    assert_eq!(write(pipe_fd[1], buf), SysError::EPIPE.as_ret());
+    assert_eq!(stat().heap_frames, heap_frames);
+    assert_eq!(close(pipe_fd[1]), 0);
+    assert_eq!(munmap(addr as usize, LARGE_WRITE), 0);
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let start = stat();
+    assert!(start.free_frames < start.total_frames);
+    assert!(start.resident_pages > 0);
+    assert!(start.page_table_pages > 0);
+    assert!(start.stack_pages > 0);
+    // the first round may leave kernel page tables and heap blocks behind
+    for test in TESTS {
+        run(test);
+    }
+    run_threads();
+    large_write();
+    let resident_pages = stat().resident_pages;
+    let available = available_frames();
+    for _ in 0..2 {
+        for test in TESTS {
+            run(test);
+        }
+        run_threads();
+        large_write();
+        // the stacks of the threads are gone with them
+        assert_eq!(stat().resident_pages, resident_pages);
+        assert_eq!(available_frames(), available);
+    }
+    println!("mem_stat_test passed!");
+    0
+}
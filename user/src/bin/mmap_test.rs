@@ -0,0 +1,138 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    exit, fork, mmap, munmap, thread_create, thread_exit, waitpid, waittid, MmapProt, SysError,
+};
+
+const PAGE_SIZE: usize = 4096;
+const FIXED_ADDR: usize = 0x2000_0000;
+/// `mmap` picks addresses from here up to the thread stacks
+const MMAP_BASE: usize = 0x10_0000_0000;
+const USER_STACK_BASE: usize = 0x20_0000_0000;
+
+fn bytes(addr: usize, len: usize) -> &'static mut [u8] {
+    unsafe { core::slice::from_raw_parts_mut(addr as *mut u8, len) }
+}
+
+/// Run `f(arg)` in a child and return its exit code
+fn run_child(f: fn(usize), arg: usize) -> i32 {
+    let pid = fork();
+    if pid == 0 {
+        f(arg);
+        exit(0);
+    }
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    exit_code
+}
+
+fn store(addr: usize) {
+    unsafe { (addr as *mut u8).write_volatile(1) };
+}
+
+fn load(addr: usize) {
+    unsafe { (addr as *const u8).read_volatile() };
+}
+
+fn worker(arg: usize) -> ! {
+    // the stack of the new thread is its own
+    let mut stack = [arg as u8; PAGE_SIZE];
+    stack[PAGE_SIZE - 1] += 1;
+    thread_exit(stack.iter().map(|&byte| byte as i32).sum::<i32>() - PAGE_SIZE as i32)
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let rw = MmapProt::READ | MmapProt::WRITE;
+    // the kernel picks the address, the memory is zeroed
+    let addr = mmap(0, 3 * PAGE_SIZE, rw);
+    assert!(addr > 0);
+    let addr = addr as usize;
+    assert_eq!(addr % PAGE_SIZE, 0);
+    let buf = bytes(addr, 3 * PAGE_SIZE);
+    assert!(buf.iter().all(|&byte| byte == 0));
+    for (i, byte) in buf.iter_mut().enumerate() {
+        *byte = (i / PAGE_SIZE) as u8 + 1;
+    }
+    // a second mapping does not overlap the first one
+    let other = mmap(0, 1, rw) as usize;
+    assert!(other >= addr + 3 * PAGE_SIZE || other + PAGE_SIZE <= addr);
+    assert_eq!(munmap(other, 1), 0);
+    // children share the pages copy-on-write
+    assert_eq!(
+        run_child(
+            |addr| {
+                assert_eq!(bytes(addr, 1)[0], 1);
+                bytes(addr, PAGE_SIZE).fill(9);
+            },
+            addr
+        ),
+        0
+    );
+    assert_eq!(bytes(addr, 1)[0], 1);
+    // unmap the middle page, the pages around it keep their data
+    assert_eq!(munmap(addr + PAGE_SIZE, PAGE_SIZE), 0);
+    assert_eq!(run_child(load, addr + PAGE_SIZE), -2);
+    assert!(bytes(addr, PAGE_SIZE).iter().all(|&byte| byte == 1));
+    assert!(bytes(addr + 2 * PAGE_SIZE, PAGE_SIZE)
+        .iter()
+        .all(|&byte| byte == 3));
+    // the hole can be mapped again and is zeroed
+    assert_eq!(
+        mmap(addr + PAGE_SIZE, PAGE_SIZE, rw),
+        (addr + PAGE_SIZE) as isize
+    );
+    assert!(bytes(addr + PAGE_SIZE, PAGE_SIZE)
+        .iter()
+        .all(|&byte| byte == 0));
+    assert_eq!(munmap(addr, 3 * PAGE_SIZE), 0);
+    assert_eq!(run_child(load, addr), -2);
+    assert_eq!(run_child(load, addr + 2 * PAGE_SIZE), -2);
+    // a fixed address, read only
+    assert_eq!(
+        mmap(FIXED_ADDR, 2 * PAGE_SIZE, MmapProt::READ),
+        FIXED_ADDR as isize
+    );
+    assert_eq!(bytes(FIXED_ADDR, 1)[0], 0);
+    assert_eq!(run_child(store, FIXED_ADDR), -2);
+    assert_eq!(
+        mmap(FIXED_ADDR + PAGE_SIZE, PAGE_SIZE, rw),
+        SysError::EEXIST.as_ret()
+    );
+    assert_eq!(munmap(FIXED_ADDR, 2 * PAGE_SIZE), 0);
+    // bad arguments
+    assert_eq!(
+        mmap(FIXED_ADDR + 1, PAGE_SIZE, rw),
+        SysError::EINVAL.as_ret()
+    );
+    assert_eq!(mmap(FIXED_ADDR, 0, rw), SysError::EINVAL.as_ret());
+    assert_eq!(
+        mmap(FIXED_ADDR, PAGE_SIZE, MmapProt::empty()),
+        SysError::EINVAL.as_ret()
+    );
+    assert_eq!(mmap(1 << 38, PAGE_SIZE, rw), SysError::ENOMEM.as_ret());
+    assert_eq!(munmap(FIXED_ADDR + 1, PAGE_SIZE), SysError::EINVAL.as_ret());
+    // the ELF image is mapped already
+    assert_eq!(
+        mmap(main as usize & !(PAGE_SIZE - 1), PAGE_SIZE, rw),
+        SysError::EEXIST.as_ret()
+    );
+    // the thread stacks are out of reach, even when the whole window is taken
+    assert_eq!(
+        mmap(USER_STACK_BASE, PAGE_SIZE, rw),
+        SysError::ENOMEM.as_ret()
+    );
+    let window = USER_STACK_BASE - MMAP_BASE;
+    assert_eq!(mmap(0, window, rw), MMAP_BASE as isize);
+    assert_eq!(mmap(0, PAGE_SIZE, rw), SysError::ENOMEM.as_ret());
+    let tid = thread_create(worker as usize, 1);
+    assert!(tid > 0);
+    assert_eq!(waittid(tid as usize), 1);
+    assert_eq!(munmap(MMAP_BASE, window), 0);
+    println!("mmap_test passed!");
+    0
+}
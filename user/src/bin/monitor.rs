@@ -9,7 +9,7 @@ use core::cell::RefMut;
 
 use alloc::{format, string::String, vec::Vec};
 use lazy_static::*;
-use user_lib::{exit, gettid, monitor_check, monitor_create, monitor_create_res_sem, monitor_enter, monitor_leave, monitor_signal, monitor_wait, sleep, thread_create, waittid, UPSafeCell};
+use user_lib::{thread_exit, gettid, monitor_check, monitor_create, monitor_create_res_sem, monitor_enter, monitor_leave, monitor_signal, monitor_wait, sleep, thread_create, waittid, UPSafeCell};
 
 ///环形缓冲池数据结构
 pub struct CycleBuf {
@@ -152,12 +152,12 @@ lazy_static! {
 pub fn processor(v: *const i32) {
     let value = unsafe { &*v };
     monitor.process(*value);
-    exit(0);
+    thread_exit(0);
 }
 ///消费者线程
 pub fn consumer() {
    monitor.consume();
-   exit(0);
+   thread_exit(0);
 }
 ///管程守护者线程
 pub fn checker() {
@@ -167,7 +167,7 @@ pub fn checker() {
             break;
         }
     }
-    exit(0);
+    thread_exit(0);
 }
 
 #[no_mangle]
@@ -0,0 +1,104 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::arch::asm;
+use user_lib::{exit, fork, mmap, mprotect, munmap, waitpid, MmapProt, SysError};
+
+const PAGE_SIZE: usize = 4096;
+/// the trap context of the main thread, owned by the kernel
+const TRAP_CONTEXT: usize = usize::MAX - 2 * PAGE_SIZE + 1;
+/// `c.jr ra`
+const RET: [u8; 2] = [0x82, 0x80];
+
+fn bytes(addr: usize, len: usize) -> &'static mut [u8] {
+    unsafe { core::slice::from_raw_parts_mut(addr as *mut u8, len) }
+}
+
+/// Run `f(arg)` in a child and return its exit code
+fn run_child(f: fn(usize), arg: usize) -> i32 {
+    let pid = fork();
+    if pid == 0 {
+        f(arg);
+        exit(0);
+    }
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    exit_code
+}
+
+fn store(addr: usize) {
+    unsafe { (addr as *mut u8).write_volatile(1) };
+}
+
+fn load(addr: usize) {
+    unsafe { (addr as *const u8).read_volatile() };
+}
+
+fn call(addr: usize) {
+    let f: extern "C" fn() = unsafe { core::mem::transmute(addr) };
+    f();
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let rw = MmapProt::READ | MmapProt::WRITE;
+    let addr = mmap(0, 3 * PAGE_SIZE, rw);
+    assert!(addr > 0);
+    let addr = addr as usize;
+    bytes(addr, 3 * PAGE_SIZE).fill(1);
+    // the middle page becomes read only, the pages around it stay writable
+    let middle = addr + PAGE_SIZE;
+    assert_eq!(mprotect(middle, PAGE_SIZE, MmapProt::READ), 0);
+    assert_eq!(run_child(store, middle), -2);
+    assert_eq!(run_child(store, addr), 0);
+    assert_eq!(run_child(store, addr + 2 * PAGE_SIZE), 0);
+    assert!(bytes(middle, PAGE_SIZE).iter().all(|&byte| byte == 1));
+    bytes(addr, PAGE_SIZE).fill(2);
+    // no access at all, the data survives until the pages are writable again
+    assert_eq!(mprotect(middle, PAGE_SIZE, MmapProt::empty()), 0);
+    assert_eq!(run_child(load, middle), -2);
+    assert_eq!(mprotect(addr, 3 * PAGE_SIZE, rw), 0);
+    assert!(bytes(middle, PAGE_SIZE).iter().all(|&byte| byte == 1));
+    bytes(middle, PAGE_SIZE).fill(3);
+    assert!(bytes(addr, PAGE_SIZE).iter().all(|&byte| byte == 2));
+    // a page shared with a child since fork stays copy-on-write
+    let pid = fork();
+    if pid == 0 {
+        assert_eq!(mprotect(addr, PAGE_SIZE, rw), 0);
+        bytes(addr, PAGE_SIZE).fill(4);
+        exit(0);
+    }
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    assert!(bytes(addr, PAGE_SIZE).iter().all(|&byte| byte == 2));
+    assert_eq!(munmap(addr, 3 * PAGE_SIZE), 0);
+    // W^X: write the code, then make it executable but no longer writable
+    let code = mmap(0, PAGE_SIZE, rw) as usize;
+    bytes(code, RET.len()).copy_from_slice(&RET);
+    assert_eq!(run_child(call, code), -2);
+    assert_eq!(
+        mprotect(code, PAGE_SIZE, MmapProt::READ | MmapProt::EXEC),
+        0
+    );
+    unsafe { asm!("fence.i") };
+    call(code);
+    assert_eq!(run_child(store, code), -2);
+    assert_eq!(munmap(code, PAGE_SIZE), 0);
+    // pages not mapped, or owned by the kernel
+    assert_eq!(mprotect(code, PAGE_SIZE, rw), SysError::ENOMEM.as_ret());
+    assert_eq!(
+        mprotect(TRAP_CONTEXT, PAGE_SIZE, rw),
+        SysError::EACCES.as_ret()
+    );
+    // bad arguments
+    let page = mmap(0, PAGE_SIZE, rw) as usize;
+    assert_eq!(mprotect(page + 1, PAGE_SIZE, rw), SysError::EINVAL.as_ret());
+    assert_eq!(mprotect(page, 0, rw), SysError::EINVAL.as_ret());
+    assert_eq!(munmap(page, PAGE_SIZE), 0);
+    println!("mprotect_test passed!");
+    0
+}
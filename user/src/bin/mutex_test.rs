@@ -7,7 +7,7 @@ extern crate alloc;
 
 use alloc::vec::Vec;
 use lazy_static::*;
-use user_lib::{exit, Mutex, thread_create, waittid, sleep};
+use user_lib::{thread_exit, Mutex, thread_create, waittid, sleep};
 
 
 static mut NUM: i32 = 30;
@@ -24,7 +24,7 @@ pub fn thread() -> ! {
         unsafe { NUM = n };
         MUTEX.unlock();
     }
-    exit(0);
+    thread_exit(0);
 }
 
 #[no_mangle]
@@ -0,0 +1,47 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, mmap, waitpid, MmapProt};
+
+const PAGE_SIZE: usize = 4096;
+/// more than the 128 MiB of physical memory and the 256 MiB of swap space
+const PAGES: usize = 512 * 256;
+/// exit code of a process killed by the OOM killer
+const OOM_EXIT_CODE: i32 = -9;
+
+fn wait_child(pid: isize) -> i32 {
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    exit_code
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let pid = fork();
+    if pid == 0 {
+        // the child has the largest resident set when memory runs out
+        let addr = mmap(0, PAGES * PAGE_SIZE, MmapProt::READ | MmapProt::WRITE);
+        assert!(addr > 0);
+        for i in 0..PAGES {
+            unsafe {
+                *((addr as usize + i * PAGE_SIZE) as *mut usize) = i;
+            }
+        }
+        println!("oom_test: the child touched every page!");
+        exit(0);
+    }
+    assert!(pid > 0);
+    assert_eq!(wait_child(pid), OOM_EXIT_CODE);
+    // the memory of the child is back
+    let pid = fork();
+    if pid == 0 {
+        exit(0);
+    }
+    assert!(pid > 0);
+    assert_eq!(wait_child(pid), 0);
+    println!("oom_test passed!");
+    0
+}
@@ -0,0 +1,102 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicIsize, Ordering};
+use lazy_static::*;
+use user_lib::{
+    get_time, sched_setscheduler, sleep, thread_create, thread_exit, waittid, Mutex, SCHED_FIFO,
+};
+
+/// the low-priority thread holds `A` this long once it runs again
+const HOLD_MS: isize = 20;
+/// the medium-priority thread spins until this long after the start
+const SPIN_MS: isize = 300;
+/// the high-priority thread gets `B` soon after the low one releases `A`
+const MAX_WAIT_MS: isize = 100;
+
+lazy_static! {
+    static ref A: Mutex = Mutex::new();
+    static ref B: Mutex = Mutex::new();
+}
+
+static START: AtomicIsize = AtomicIsize::new(0);
+static HIGH_LOCKED: AtomicIsize = AtomicIsize::new(0);
+static SPIN_DONE: AtomicIsize = AtomicIsize::new(0);
+static LOW_RESUMED: AtomicIsize = AtomicIsize::new(0);
+
+fn sleep_until(time: isize) {
+    sleep((time - get_time()).max(0) as usize);
+}
+
+/// holds `A` while sleeping, then spins with it
+fn low(_arg: usize) -> ! {
+    assert_eq!(sched_setscheduler(SCHED_FIFO, 10), 0);
+    let start = START.load(Ordering::Relaxed);
+    sleep_until(start);
+    assert_eq!(A.lock(), 0);
+    sleep_until(start + 20);
+    let end = get_time() + HOLD_MS;
+    while get_time() < end {}
+    assert_eq!(A.unlock(), 0);
+    // back at its own priority, behind the spinner
+    LOW_RESUMED.store(get_time(), Ordering::Relaxed);
+    thread_exit(0)
+}
+
+/// holds `B` while waiting for `A`
+fn mid(_arg: usize) -> ! {
+    assert_eq!(sched_setscheduler(SCHED_FIFO, 20), 0);
+    sleep_until(START.load(Ordering::Relaxed) + 5);
+    assert_eq!(B.lock(), 0);
+    assert_eq!(A.lock(), 0);
+    assert_eq!(A.unlock(), 0);
+    assert_eq!(B.unlock(), 0);
+    thread_exit(0)
+}
+
+/// waits for `B`, boosting `mid` and through it `low`
+fn high(_arg: usize) -> ! {
+    assert_eq!(sched_setscheduler(SCHED_FIFO, 90), 0);
+    sleep_until(START.load(Ordering::Relaxed) + 10);
+    assert_eq!(B.lock(), 0);
+    HIGH_LOCKED.store(get_time(), Ordering::Relaxed);
+    assert_eq!(B.unlock(), 0);
+    thread_exit(0)
+}
+
+/// starves everything below its priority
+fn spinner(_arg: usize) -> ! {
+    assert_eq!(sched_setscheduler(SCHED_FIFO, 50), 0);
+    let start = START.load(Ordering::Relaxed);
+    sleep_until(start + 12);
+    while get_time() < start + SPIN_MS {}
+    SPIN_DONE.store(get_time(), Ordering::Relaxed);
+    thread_exit(0)
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let start = get_time() + 20;
+    START.store(start, Ordering::Relaxed);
+    let tids = [low as usize, mid as usize, high as usize, spinner as usize]
+        .map(|entry| thread_create(entry, 0) as usize);
+    for tid in tids {
+        assert_eq!(waittid(tid), 0);
+    }
+    let high_locked = HIGH_LOCKED.load(Ordering::Relaxed) - start;
+    let spin_done = SPIN_DONE.load(Ordering::Relaxed) - start;
+    let low_resumed = LOW_RESUMED.load(Ordering::Relaxed) - start;
+    println!(
+        "high locked at {}ms, spinner done at {}ms, low resumed at {}ms",
+        high_locked, spin_done, low_resumed
+    );
+    // the priority of `high` passes through `mid` to `low`
+    assert!(high_locked < MAX_WAIT_MS);
+    // and is given up on `unlock`
+    assert!(low_resumed >= spin_done);
+    println!("pi_test passed!");
+    0
+}
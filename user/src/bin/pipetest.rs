@@ -0,0 +1,93 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, fork, pipe, read, wait, waitpid, write, SysError};
+
+static STR: &str = "Hello, world!";
+
+/// more than the pipe can hold, so both sides have to block
+const LARGE_LEN: usize = 4096 * 3 + 17;
+/// each write is also larger than the pipe
+const CHUNK_LEN: usize = 1024;
+
+fn byte_at(i: usize) -> u8 {
+    (i % 251) as u8
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // parent writes to the child through a pipe created before fork
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    let pid = fork();
+    if pid == 0 {
+        // child process, read from parent
+        close(pipe_fd[1]);
+        let mut buffer = [0u8; 32];
+        let len_read = read(pipe_fd[0], &mut buffer) as usize;
+        assert_eq!(core::str::from_utf8(&buffer[..len_read]).unwrap(), STR);
+        // all write ends are closed after the parent closes its own
+        assert_eq!(read(pipe_fd[0], &mut buffer), 0);
+        close(pipe_fd[0]);
+        println!("Read OK, child process exited!");
+        return 0;
+    }
+    // parent process, write to child
+    close(pipe_fd[0]);
+    assert_eq!(write(pipe_fd[1], STR.as_bytes()), STR.len() as isize);
+    close(pipe_fd[1]);
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+
+    // a large transfer from child to parent
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    let pid = fork();
+    if pid == 0 {
+        close(pipe_fd[0]);
+        let mut buffer = [0u8; CHUNK_LEN];
+        let mut total = 0;
+        while total < LARGE_LEN {
+            let len = CHUNK_LEN.min(LARGE_LEN - total);
+            for (i, byte) in buffer[..len].iter_mut().enumerate() {
+                *byte = byte_at(total + i);
+            }
+            assert_eq!(write(pipe_fd[1], &buffer[..len]), len as isize);
+            total += len;
+        }
+        // exiting closes the write end
+        return 0;
+    }
+    close(pipe_fd[1]);
+    let mut buffer = [0u8; 100];
+    let mut total = 0;
+    loop {
+        let len_read = read(pipe_fd[0], &mut buffer);
+        assert!(len_read >= 0);
+        if len_read == 0 {
+            break;
+        }
+        for (i, &byte) in buffer[..len_read as usize].iter().enumerate() {
+            assert_eq!(byte, byte_at(total + i));
+        }
+        total += len_read as usize;
+    }
+    assert_eq!(total, LARGE_LEN);
+    close(pipe_fd[0]);
+    assert_eq!(wait(&mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+
+    // writing with no reader left fails
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    close(pipe_fd[0]);
+    assert_eq!(write(pipe_fd[1], STR.as_bytes()), SysError::EPIPE.as_ret());
+    // each end can only be used in its own direction
+    let mut buffer = [0u8; 1];
+    assert_eq!(read(pipe_fd[1], &mut buffer), SysError::EBADF.as_ret());
+    close(pipe_fd[1]);
+    println!("pipetest passed!");
+    0
+}
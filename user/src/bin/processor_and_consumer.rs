@@ -6,7 +6,7 @@ extern crate user_lib;
 extern crate alloc;
 
 use alloc::{format, string::String, vec::Vec};
-use user_lib::{exit, gettid, sleep, thread_create, waittid};
+use user_lib::{thread_exit, gettid, sleep, thread_create, waittid};
 
 struct CycleBuf {
     read: usize,
@@ -51,7 +51,7 @@ pub fn processor(v: *const i32) {
             HISTORY.push(history);
         }
     }
-    exit(0);
+    thread_exit(0);
 }
 
 pub fn consumer() {
@@ -63,7 +63,7 @@ pub fn consumer() {
             HISTORY.push(history);
         }
     }
-    exit(0);
+    thread_exit(0);
 }
 
 
@@ -0,0 +1,112 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicIsize, Ordering};
+use user_lib::{
+    exit, fork, get_time, sched_setscheduler, sleep, thread_create, thread_exit, waitpid,
+    waitpid_nohang, waittid, SysError, SCHED_FIFO, SCHED_NORMAL, SCHED_RR,
+};
+
+/// a woken real-time thread preempts the lower-priority one at once
+const MAX_LATENCY_MS: isize = 10;
+/// round robin threads of the same priority take turns every 100ms
+const RR_MS: isize = 300;
+/// a runaway real-time process spins this long
+const RUNAWAY_MS: isize = 3000;
+
+static WOKEN_AT: AtomicIsize = AtomicIsize::new(0);
+static RR_START: AtomicIsize = AtomicIsize::new(0);
+static RR_FIRST_RUN: [AtomicIsize; 2] = [AtomicIsize::new(0), AtomicIsize::new(0)];
+
+fn spin_until(end: isize) {
+    while get_time() < end {}
+}
+
+fn sleeper(_arg: usize) -> ! {
+    assert_eq!(sched_setscheduler(SCHED_FIFO, 90), 0);
+    sleep(20);
+    WOKEN_AT.store(get_time(), Ordering::Relaxed);
+    thread_exit(0)
+}
+
+/// `main` spins as a SCHED_FIFO thread while a thread of a higher priority
+/// sleeps, the latter runs as soon as its timer expires
+fn test_wakeup_preemption() {
+    let tid = thread_create(sleeper as usize, 0) as usize;
+    // let the sleeper move into its class and fall asleep
+    sleep(5);
+    assert_eq!(sched_setscheduler(SCHED_FIFO, 50), 0);
+    let start = get_time();
+    while WOKEN_AT.load(Ordering::Relaxed) == 0 {
+        assert!(get_time() < start + 1000, "the sleeper is never woken up");
+    }
+    let woken_at = WOKEN_AT.load(Ordering::Relaxed);
+    assert_eq!(sched_setscheduler(SCHED_NORMAL, 0), 0);
+    assert_eq!(waittid(tid), 0);
+    println!(
+        "woken up {}ms after main started spinning",
+        woken_at - start
+    );
+    assert!(woken_at - start < 20 + MAX_LATENCY_MS);
+}
+
+fn round_robin(idx: usize) -> ! {
+    assert_eq!(sched_setscheduler(SCHED_RR, 20), 0);
+    let start = RR_START.load(Ordering::Relaxed);
+    // sleep so that the other thread moves into its class too
+    sleep((start - get_time()).max(0) as usize);
+    RR_FIRST_RUN[idx].store(get_time(), Ordering::Relaxed);
+    spin_until(start + RR_MS);
+    thread_exit(0)
+}
+
+/// two SCHED_RR threads of the same priority both run before either ends
+fn test_round_robin() {
+    RR_START.store(get_time() + 50, Ordering::Relaxed);
+    let tids = [0, 1].map(|idx| thread_create(round_robin as usize, idx) as usize);
+    for tid in tids {
+        assert_eq!(waittid(tid), 0);
+    }
+    let [a, b] = RR_FIRST_RUN.each_ref().map(|t| t.load(Ordering::Relaxed));
+    println!("round robin threads first ran at {} and {}", a, b);
+    assert!((a - b).abs() < RR_MS / 2);
+}
+
+/// a real-time process spinning forever does not starve normal ones
+fn test_throttling() {
+    let pid = fork();
+    if pid == 0 {
+        assert_eq!(sched_setscheduler(SCHED_FIFO, 99), 0);
+        spin_until(get_time() + RUNAWAY_MS);
+        exit(0);
+    }
+    assert!(pid > 0);
+    let start = get_time();
+    sleep(100);
+    let mut exit_code = 0;
+    // the runaway process is still spinning
+    assert_eq!(waitpid_nohang(pid, &mut exit_code), 0);
+    println!("normal process ran {}ms later", get_time() - start);
+    assert!(get_time() - start < RUNAWAY_MS);
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    assert_eq!(sched_setscheduler(SCHED_FIFO, 0), SysError::EINVAL.as_ret());
+    assert_eq!(sched_setscheduler(SCHED_RR, 100), SysError::EINVAL.as_ret());
+    assert_eq!(
+        sched_setscheduler(SCHED_NORMAL, 5),
+        SysError::EINVAL.as_ret()
+    );
+    assert_eq!(sched_setscheduler(3, 0), SysError::EINVAL.as_ret());
+    test_wakeup_preemption();
+    test_round_robin();
+    test_throttling();
+    println!("rt_test passed!");
+    0
+}
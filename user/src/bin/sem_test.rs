@@ -6,7 +6,7 @@ extern crate user_lib;
 extern crate alloc;
 use alloc::{format, string::String, vec::Vec};
 use lazy_static::*;
-use user_lib::{exit, gettid, Semaphore, sleep, thread_create, waittid};
+use user_lib::{thread_exit, gettid, Semaphore, sleep, thread_create, waittid};
 
 ///环形缓冲池
 struct CycleBuf {
@@ -65,7 +65,7 @@ pub fn processor(v: *const i32) {
             FULL.post(); //唤醒正在等待的消费者线程
         }
     }
-    exit(0);
+    thread_exit(0);
 }
 
 pub fn consumer() {
@@ -81,7 +81,7 @@ pub fn consumer() {
             EMPTY.post();  //唤醒正在等待的生产者线程
         }
     }
-    exit(0);
+    thread_exit(0);
 }
 
 
@@ -0,0 +1,102 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, mprotect, munmap, shmat, waitpid, MmapProt, SysError};
+
+const PAGE_SIZE: usize = 4096;
+const FIXED_ADDR: usize = 0x2000_0000;
+const KEY: usize = 0x53_484d;
+
+fn bytes(addr: usize, len: usize) -> &'static mut [u8] {
+    unsafe { core::slice::from_raw_parts_mut(addr as *mut u8, len) }
+}
+
+/// Run `f(arg)` in a child and return its exit code
+fn run_child(f: fn(usize), arg: usize) -> i32 {
+    let pid = fork();
+    if pid == 0 {
+        f(arg);
+        exit(0);
+    }
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    exit_code
+}
+
+fn store(addr: usize) {
+    unsafe { (addr as *mut u8).write_volatile(1) };
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // private shared memory, zeroed and still shared after fork
+    let addr = shmat(0, 2 * PAGE_SIZE, 0);
+    assert!(addr > 0);
+    let addr = addr as usize;
+    assert!(bytes(addr, 2 * PAGE_SIZE).iter().all(|&byte| byte == 0));
+    bytes(addr, PAGE_SIZE).fill(1);
+    assert_eq!(
+        run_child(
+            |addr| {
+                assert!(bytes(addr, PAGE_SIZE).iter().all(|&byte| byte == 1));
+                bytes(addr, 2 * PAGE_SIZE).fill(2);
+            },
+            addr
+        ),
+        0
+    );
+    assert!(bytes(addr, 2 * PAGE_SIZE).iter().all(|&byte| byte == 2));
+    // made read only in a child, the parent keeps writing
+    assert_eq!(
+        run_child(
+            |addr| {
+                assert_eq!(mprotect(addr, PAGE_SIZE, MmapProt::READ), 0);
+                assert_eq!(run_child(store, addr), -2);
+                bytes(addr + PAGE_SIZE, PAGE_SIZE).fill(3);
+            },
+            addr
+        ),
+        0
+    );
+    assert_eq!(bytes(addr + PAGE_SIZE, 1)[0], 3);
+    bytes(addr, PAGE_SIZE).fill(4);
+    assert_eq!(munmap(addr, 2 * PAGE_SIZE), 0);
+    // named shared memory, attached twice and by a child at its own address
+    let first = shmat(KEY, PAGE_SIZE, 0) as usize;
+    assert_eq!(shmat(KEY, PAGE_SIZE, FIXED_ADDR), FIXED_ADDR as isize);
+    bytes(first, 5).copy_from_slice(b"hello");
+    assert_eq!(bytes(FIXED_ADDR, 5), b"hello");
+    assert_eq!(munmap(first, PAGE_SIZE), 0);
+    assert_eq!(
+        run_child(
+            |_| {
+                assert_eq!(munmap(FIXED_ADDR, PAGE_SIZE), 0);
+                let addr = shmat(KEY, PAGE_SIZE, 0) as usize;
+                assert_eq!(bytes(addr, 5), b"hello");
+                bytes(addr, 5).copy_from_slice(b"world");
+            },
+            0
+        ),
+        0
+    );
+    assert_eq!(bytes(FIXED_ADDR, 5), b"world");
+    // longer than the existing shared memory, or in the way of an attachment
+    assert_eq!(shmat(KEY, 2 * PAGE_SIZE, 0), SysError::EINVAL.as_ret());
+    assert_eq!(shmat(KEY, PAGE_SIZE, FIXED_ADDR), SysError::EEXIST.as_ret());
+    // freed with the last attachment, attaching again gives zeroed memory
+    assert_eq!(munmap(FIXED_ADDR, PAGE_SIZE), 0);
+    let addr = shmat(KEY, 2 * PAGE_SIZE, 0) as usize;
+    assert!(bytes(addr, 2 * PAGE_SIZE).iter().all(|&byte| byte == 0));
+    assert_eq!(munmap(addr, 2 * PAGE_SIZE), 0);
+    // bad arguments
+    assert_eq!(shmat(KEY, 0, 0), SysError::EINVAL.as_ret());
+    assert_eq!(
+        shmat(KEY, PAGE_SIZE, FIXED_ADDR + 1),
+        SysError::EINVAL.as_ret()
+    );
+    println!("shm_test passed!");
+    0
+}
@@ -0,0 +1,64 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use user_lib::{get_time, set_priority, thread_create, thread_exit, waittid, SysError};
+
+/// each worker gets a share of the CPU proportional to its priority
+const PRIOS: [isize; 3] = [4, 8, 16];
+const DURATION_MS: isize = 1000;
+const CHUNK: usize = 10000;
+
+static START: AtomicUsize = AtomicUsize::new(0);
+static COUNTS: [AtomicUsize; PRIOS.len()] = [
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+];
+
+fn spin(n: usize) {
+    for i in 0..n {
+        core::hint::black_box(i);
+    }
+}
+
+fn worker(idx: usize) -> ! {
+    assert_eq!(set_priority(PRIOS[idx]), PRIOS[idx]);
+    let start = START.load(Ordering::Relaxed) as isize;
+    // all the workers are created before they start counting
+    while get_time() < start {}
+    let mut count = 0;
+    while get_time() < start + DURATION_MS {
+        spin(CHUNK);
+        count += 1;
+    }
+    COUNTS[idx].store(count, Ordering::Relaxed);
+    thread_exit(0)
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    assert_eq!(set_priority(1), SysError::EINVAL.as_ret());
+    assert_eq!(set_priority(16), 16);
+    START.store(get_time() as usize + 100, Ordering::Relaxed);
+    let mut tids = [0usize; PRIOS.len()];
+    for (idx, tid) in tids.iter_mut().enumerate() {
+        *tid = thread_create(worker as usize, idx) as usize;
+    }
+    for tid in tids {
+        assert_eq!(waittid(tid), 0);
+    }
+    let counts = COUNTS.each_ref().map(|count| count.load(Ordering::Relaxed));
+    for (prio, count) in PRIOS.iter().zip(counts) {
+        println!("priority {}: {} chunks", prio, count);
+    }
+    assert!(counts[0] > 0);
+    assert!(counts[0] < counts[1] && counts[1] < counts[2]);
+    // 4 times as much in theory
+    assert!(counts[2] > counts[0] * 2);
+    println!("stride_test passed!");
+    0
+}
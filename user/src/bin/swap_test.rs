@@ -0,0 +1,80 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, mem_stat, mmap, munmap, pipe, read, write, MemStat, MmapProt};
+
+const PAGE_SIZE: usize = 4096;
+/// more than the 128 MiB of physical memory
+const PAGES: usize = 144 * 256;
+/// pages checked again, spread over the whole mapping
+const CHECK_STEP: usize = 16;
+
+fn page(addr: usize, i: usize) -> &'static mut [usize] {
+    unsafe {
+        core::slice::from_raw_parts_mut(
+            (addr + i * PAGE_SIZE) as *mut usize,
+            PAGE_SIZE / core::mem::size_of::<usize>(),
+        )
+    }
+}
+
+fn bytes(addr: usize, len: usize) -> &'static mut [u8] {
+    unsafe { core::slice::from_raw_parts_mut(addr as *mut u8, len) }
+}
+
+fn swap_stat() -> MemStat {
+    let mut stat = MemStat::default();
+    assert_eq!(mem_stat(&mut stat), 0);
+    stat
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let before = swap_stat();
+    let addr = mmap(0, PAGES * PAGE_SIZE, MmapProt::READ | MmapProt::WRITE);
+    assert!(addr > 0);
+    let addr = addr as usize;
+    // the first pages are swapped out long before the last ones get frames
+    for i in 0..PAGES {
+        let page = page(addr, i);
+        page[0] = i;
+        let last = page.len() - 1;
+        page[last] = !i;
+        if i % 4096 == 0 {
+            println!("swap_test: {} of {} pages touched", i, PAGES);
+        }
+    }
+    // read back from the swap device
+    for i in (0..PAGES).step_by(CHECK_STEP) {
+        let page = page(addr, i);
+        assert_eq!(page[0], i);
+        assert_eq!(page[page.len() - 1], !i);
+    }
+    // the pages did not fit in memory, so some went to the swap device and back
+    let after = swap_stat();
+    println!(
+        "swap_test: {} pages swapped out, {} swapped in",
+        after.swap_page_outs - before.swap_page_outs,
+        after.swap_page_ins - before.swap_page_ins
+    );
+    assert!(after.swap_page_outs > before.swap_page_outs);
+    assert!(after.swap_page_ins > before.swap_page_ins);
+    // the kernel swaps in both pages of a buffer crossing them, and keeps the
+    // first one while it brings in the second
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    let src = addr + PAGE_SIZE - 8;
+    assert_eq!(write(pipe_fd[1], bytes(src, 16)), 16);
+    let dst = addr + (PAGES / 2 + 1) * PAGE_SIZE - 8;
+    assert_eq!(read(pipe_fd[0], bytes(dst, 16)), 16);
+    assert_eq!(page(addr, PAGES / 2)[PAGE_SIZE / 8 - 1], !0);
+    assert_eq!(page(addr, PAGES / 2 + 1)[0], 1);
+    close(pipe_fd[0]);
+    close(pipe_fd[1]);
+    assert_eq!(munmap(addr, PAGES * PAGE_SIZE), 0);
+    println!("swap_test passed!");
+    0
+}
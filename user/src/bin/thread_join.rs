@@ -0,0 +1,31 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{sleep, thread_create, thread_detach, thread_exit, waittid, SysError};
+
+fn worker(rc: usize) -> ! {
+    sleep(20);
+    thread_exit(rc as i32)
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let joined = thread_create(worker as usize, 3) as usize;
+    let detached = thread_create(worker as usize, 4) as usize;
+    assert_eq!(thread_detach(detached), 0);
+    assert_eq!(waittid(detached), SysError::EINVAL.as_ret());
+    // blocks until the worker exits
+    assert_eq!(waittid(joined), 3);
+    // a thread can only be joined once
+    assert_eq!(waittid(joined), SysError::ESRCH.as_ret());
+    // the detached worker is reclaimed as soon as it exits, it can not be joined
+    sleep(50);
+    assert_eq!(waittid(detached), SysError::ESRCH.as_ret());
+    let reused = thread_create(worker as usize, 5) as usize;
+    assert_eq!(waittid(reused), 5);
+    println!("thread_join passed!");
+    0
+}
@@ -6,7 +6,7 @@ extern crate user_lib;
 extern crate alloc;
 
 use alloc::vec::Vec;
-use user_lib::{exit, thread_create, waittid};
+use user_lib::{thread_exit, thread_create, waittid};
 
 struct Argument {
     pub ch: char,
@@ -18,7 +18,7 @@ fn thread_print(arg: *const Argument) -> ! {
     for _ in 0..1000 {
         print!("{}", arg.ch);
     }
-    exit(arg.rc)
+    thread_exit(arg.rc)
 }
 
 #[no_mangle]
@@ -9,16 +9,37 @@ extern crate user_lib;
 
 // item of TESTS : app_name(argv_0), argv_1, argv_2, argv_3, exit_code
 static SUCC_TESTS: &[(&str, &str, &str, &str, i32)] = &[
+    ("bad_address\0", "\0", "\0", "\0", 0),
+    ("brk_test\0", "\0", "\0", "\0", 0),
+    ("cow_test\0", "\0", "\0", "\0", 0),
+    ("edf_test\0", "\0", "\0", "\0", 0),
     ("exit\0", "\0", "\0", "\0", 0),
     ("fantastic_text\0", "\0", "\0", "\0", 0),
+    ("fd_table\0", "\0", "\0", "\0", 0),
+    ("file_test\0", "\0", "\0", "\0", 0),
     ("forktest_simple\0", "\0", "\0", "\0", 0),
     ("forktest\0", "\0", "\0", "\0", 0),
     ("forktest2\0", "\0", "\0", "\0", 0),
     ("forktree\0", "\0", "\0", "\0", 0),
+    ("heap_grow_test\0", "\0", "\0", "\0", 0),
     ("hello_world\0", "\0", "\0", "\0", 0),
+    ("interactive_test\0", "\0", "\0", "\0", 0),
+    ("lazy_test\0", "\0", "\0", "\0", 0),
     ("matrix\0", "\0", "\0", "\0", 0),
+    ("mem_stat_test\0", "\0", "\0", "\0", 0),
+    ("mmap_test\0", "\0", "\0", "\0", 0),
+    ("mprotect_test\0", "\0", "\0", "\0", 0),
+    ("oom_test\0", "\0", "\0", "\0", 0),
+    ("pi_test\0", "\0", "\0", "\0", 0),
+    ("pipetest\0", "\0", "\0", "\0", 0),
+    ("rt_test\0", "\0", "\0", "\0", 0),
+    ("shm_test\0", "\0", "\0", "\0", 0),
     ("sleep_simple\0", "\0", "\0", "\0", 0),
     ("sleep\0", "\0", "\0", "\0", 0),
+    ("stride_test\0", "\0", "\0", "\0", 0),
+    ("swap_test\0", "\0", "\0", "\0", 0),
+    ("thread_join\0", "\0", "\0", "\0", 0),
+    ("wait_block\0", "\0", "\0", "\0", 0),
     ("yield\0", "\0", "\0", "\0", 0),
 ];
 
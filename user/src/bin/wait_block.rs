@@ -0,0 +1,28 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, get_time, sleep, waitpid, waitpid_nohang, SysError};
+
+const EXIT_CODE: i32 = 7;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let pid = fork();
+    if pid == 0 {
+        sleep(100);
+        exit(EXIT_CODE);
+    }
+    let mut exit_code: i32 = 0;
+    // the child is still sleeping
+    assert_eq!(waitpid_nohang(pid, &mut exit_code), 0);
+    let start = get_time();
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, EXIT_CODE);
+    println!("waited {} ms for child {}", get_time() - start, pid);
+    assert_eq!(waitpid_nohang(-1, &mut exit_code), SysError::ECHILD.as_ret());
+    println!("wait_block passed!");
+    0
+}
@@ -0,0 +1,69 @@
+//! 系统调用错误码，与内核 `os::syscall::SysError` 一一对应
+
+///系统调用错误码
+#[allow(clippy::upper_case_acronyms)]
+#[repr(isize)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SysError {
+    EPERM = 1,    //操作不允许
+    ENOENT = 2,   //文件或程序不存在
+    ESRCH = 3,    //进程或线程不存在
+    EINTR = 4,    //被中断
+    EIO = 5,      //输入输出错误
+    EBADF = 9,    //无效的文件描述符
+    ECHILD = 10,  //没有可等待的子进程
+    EAGAIN = 11,  //资源暂时不可用
+    ENOMEM = 12,  //内存不足
+    EACCES = 13,  //权限不足
+    EFAULT = 14,  //非法的用户地址
+    EBUSY = 16,   //资源忙
+    EEXIST = 17,  //文件已存在
+    ENOTDIR = 20, //不是目录
+    EISDIR = 21,  //是目录
+    EINVAL = 22,  //无效参数
+    EMFILE = 24,  //打开的文件过多
+    ENOSPC = 28,  //设备空间不足
+    ESPIPE = 29,  //非法的定位操作
+    EPIPE = 32,   //管道读端已关闭
+    EDEADLK = 35, //检测到死锁
+    ENAMETOOLONG = 36, //文件名过长
+    ENOSYS = 38,  //系统调用未实现
+}
+
+impl SysError {
+    ///从系统调用返回值中解析错误码，非负返回值表示调用成功
+    pub fn from_ret(ret: isize) -> Option<Self> {
+        use SysError::*;
+        let err = match -ret {
+            1 => EPERM,
+            2 => ENOENT,
+            3 => ESRCH,
+            4 => EINTR,
+            5 => EIO,
+            9 => EBADF,
+            10 => ECHILD,
+            11 => EAGAIN,
+            12 => ENOMEM,
+            13 => EACCES,
+            14 => EFAULT,
+            16 => EBUSY,
+            17 => EEXIST,
+            20 => ENOTDIR,
+            21 => EISDIR,
+            22 => EINVAL,
+            24 => EMFILE,
+            28 => ENOSPC,
+            29 => ESPIPE,
+            32 => EPIPE,
+            35 => EDEADLK,
+            36 => ENAMETOOLONG,
+            38 => ENOSYS,
+            _ => return None,
+        };
+        Some(err)
+    }
+    ///转换为系统调用的返回值
+    pub fn as_ret(self) -> isize {
+        -(self as isize)
+    }
+}
@@ -5,19 +5,48 @@
 
 #[macro_use]
 pub mod console;
+mod errno;
 mod lang_items;
 mod syscall;
 
+pub use errno::SysError;
 use buddy_system_allocator::LockedHeap;
 use syscall::*;
+use core::alloc::{GlobalAlloc, Layout};
 use core::cell::{RefCell, RefMut};
+use core::ptr::{null_mut, NonNull};
 
-const USER_HEAP_SIZE: usize = 16384;
+#[macro_use]
+extern crate bitflags;
+
+///堆每次至少增长的字节数
+const HEAP_GROW_SIZE: usize = 16384;
 
-static mut HEAP_SPACE: [u8; USER_HEAP_SIZE] = [0; USER_HEAP_SIZE];
+///用户堆，空间不足时通过 `sbrk` 向内核申请更多内存
+struct UserHeap(LockedHeap);
+
+unsafe impl GlobalAlloc for UserHeap {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let mut heap = self.0.lock();
+        if let Ok(ptr) = heap.alloc(layout) {
+            return ptr.as_ptr();
+        }
+        // 伙伴系统的块按自身大小对齐，两倍大小的空间一定能放下一个块
+        let size = (layout.size().max(layout.align()).next_power_of_two() * 2).max(HEAP_GROW_SIZE);
+        let start = sbrk(size as isize);
+        if start < 0 {
+            return null_mut();
+        }
+        heap.add_to_heap(start as usize, start as usize + size);
+        heap.alloc(layout).map_or(null_mut(), |ptr| ptr.as_ptr())
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.0.lock().dealloc(NonNull::new_unchecked(ptr), layout);
+    }
+}
 
 #[global_allocator]
-static HEAP: LockedHeap = LockedHeap::empty();
+static HEAP: UserHeap = UserHeap(LockedHeap::empty());
 
 #[alloc_error_handler]
 pub fn handle_alloc_error(layout: core::alloc::Layout) -> ! {
@@ -27,10 +56,6 @@ pub fn handle_alloc_error(layout: core::alloc::Layout) -> ! {
 #[no_mangle]
 #[link_section = ".text.entry"]
 pub extern "C" fn _start() -> ! {
-    unsafe {
-        HEAP.lock()
-            .init(HEAP_SPACE.as_ptr() as usize, USER_HEAP_SIZE);
-    }
     exit(main());
 }
 
@@ -105,18 +130,167 @@ impl<T> UPSafeCell<T> {
     }
 }
 
+/// duplicates `fd` to the lowest free descriptor
+pub fn dup(fd: usize) -> isize {
+    sys_dup(fd)
+}
+/// makes `new_fd` refer to the same file as `old_fd`, closing `new_fd` first
+pub fn dup2(old_fd: usize, new_fd: usize) -> isize {
+    sys_dup2(old_fd, new_fd)
+}
+bitflags! {
+    ///`open` 的标志
+    pub struct OpenFlags: u32 {
+        ///只写
+        const WRONLY = 1 << 0;
+        ///读写
+        const RDWR = 1 << 1;
+        ///文件不存在时创建
+        const CREATE = 1 << 6;
+        ///把文件截断为 0 字节
+        const TRUNC = 1 << 9;
+        ///每次写入都追加到文件末尾
+        const APPEND = 1 << 10;
+    }
+}
+
+impl OpenFlags {
+    ///只读，不设置访问模式位
+    pub const RDONLY: Self = Self::empty();
+}
+
+bitflags! {
+    ///文件类型
+    pub struct StatMode: u32 {
+        ///管道
+        const FIFO = 0o010000;
+        ///字符设备
+        const CHR = 0o020000;
+        ///目录
+        const DIR = 0o040000;
+        ///普通文件
+        const FILE = 0o100000;
+    }
+}
+
+///`fstat` 返回的文件状态，与内核中的布局一致
+#[repr(C)]
+#[derive(Debug)]
+pub struct Stat {
+    ///设备号
+    pub dev: u64,
+    ///inode 号
+    pub ino: u64,
+    ///文件类型
+    pub mode: StatMode,
+    ///硬链接数，已被 unlink 的文件为 0
+    pub nlink: u32,
+    ///文件大小（字节）
+    pub size: u64,
+}
+
+impl Stat {
+    ///创建一个全零的 `Stat`
+    pub fn new() -> Self {
+        Self {
+            dev: 0,
+            ino: 0,
+            mode: StatMode::empty(),
+            nlink: 0,
+            size: 0,
+        }
+    }
+}
+
+impl Default for Stat {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+///`lseek` 的 `whence`：从文件开头计算
+pub const SEEK_SET: usize = 0;
+///`lseek` 的 `whence`：从当前位置计算
+pub const SEEK_CUR: usize = 1;
+///`lseek` 的 `whence`：从文件末尾计算
+pub const SEEK_END: usize = 2;
+
+/// opens `path` (ending with `\0`) in the root directory and returns its fd
+pub fn open(path: &str, flags: OpenFlags) -> isize {
+    sys_open(path, flags.bits())
+}
+pub fn close(fd: usize) -> isize {
+    sys_close(fd)
+}
+/// creates a pipe, `pipe_fd[0]` gets the read end and `pipe_fd[1]` the write end
+pub fn pipe(pipe_fd: &mut [usize; 2]) -> isize {
+    sys_pipe(pipe_fd)
+}
+/// moves the offset of `fd` and returns the new offset
+pub fn lseek(fd: usize, offset: isize, whence: usize) -> isize {
+    sys_lseek(fd, offset, whence)
+}
+pub fn fstat(fd: usize, st: &mut Stat) -> isize {
+    sys_fstat(fd, st)
+}
+/// removes `path` (ending with `\0`), its data is kept until the last fd is closed
+pub fn unlink(path: &str) -> isize {
+    sys_unlink(path)
+}
 pub fn read(fd: usize, buf: &mut [u8]) -> isize {
     sys_read(fd, buf)
 }
 pub fn write(fd: usize, buf: &[u8]) -> isize {
     sys_write(fd, buf)
 }
+/// exits the whole process, no matter which thread calls it
 pub fn exit(exit_code: i32) -> ! {
     sys_exit(exit_code);
 }
 pub fn yield_() -> isize {
     sys_yield()
 }
+/// sets the priority of the calling thread (at least 2), its share of the CPU grows with it
+pub fn set_priority(prio: isize) -> isize {
+    sys_set_priority(prio)
+}
+///普通线程的调度类别
+pub const SCHED_NORMAL: usize = 0;
+///实时线程，运行到阻塞、让出或被更高优先级的线程抢占
+pub const SCHED_FIFO: usize = 1;
+///实时线程，同优先级的线程之间按时间片轮转
+pub const SCHED_RR: usize = 2;
+
+/// moves the calling thread into the class `policy`, with a priority from 1 to 99 for the
+/// real-time ones and 0 for `SCHED_NORMAL`
+pub fn sched_setscheduler(policy: usize, priority: usize) -> isize {
+    sys_sched_setscheduler(policy, priority)
+}
+///`periodic_stat` 返回的周期线程统计，与内核中的布局一致
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PeriodicStat {
+    ///完成的周期数
+    pub jobs: usize,
+    ///错过截止时间的周期数
+    pub deadline_misses: usize,
+    ///用完预算后被限制的次数
+    pub budget_overruns: usize,
+}
+
+/// makes the calling thread periodic from now on: every `period` ms it runs for at most `budget`
+/// ms, and should be done `deadline` ms after the period starts. `period` is an hour at most
+pub fn set_periodic(period: usize, budget: usize, deadline: usize) -> isize {
+    sys_set_periodic(period, budget, deadline)
+}
+/// blocks the calling periodic thread until its next period starts
+pub fn wait_next_period() -> isize {
+    sys_wait_next_period()
+}
+/// fills `stat` with the counters of the calling periodic thread
+pub fn periodic_stat(stat: &mut PeriodicStat) -> isize {
+    sys_periodic_stat(stat)
+}
 pub fn get_time() -> isize {
     sys_get_time()
 }
@@ -129,29 +303,98 @@ pub fn fork() -> isize {
 pub fn exec(path: &str) -> isize {
     sys_exec(path)
 }
-pub fn wait(exit_code: &mut i32) -> isize {
-    loop {
-        match sys_waitpid(-1, exit_code as *mut _) {
-            -2 => {
-                yield_();
-            }
-            // -1 or a real pid
-            exit_pid => return exit_pid,
-        }
+bitflags! {
+    ///`mmap` 映射的内存的保护位
+    pub struct MmapProt: usize {
+        ///可读
+        const READ = 1 << 0;
+        ///可写，同时可读
+        const WRITE = 1 << 1;
+        ///可执行
+        const EXEC = 1 << 2;
     }
 }
 
-pub fn waitpid(pid: usize, exit_code: &mut i32) -> isize {
-    loop {
-        match sys_waitpid(pid as isize, exit_code as *mut _) {
-            -2 => {
-                yield_();
-            }
-            // -1 or a real pid
-            exit_pid => return exit_pid,
-        }
+/// maps `len` zeroed bytes at the page-aligned `addr`, or anywhere if `addr` is 0,
+/// returns the start address
+pub fn mmap(addr: usize, len: usize, prot: MmapProt) -> isize {
+    sys_mmap(addr, len, prot.bits())
+}
+/// unmaps the pages in `[addr, addr + len)`, splitting mappings partly in the range
+pub fn munmap(addr: usize, len: usize) -> isize {
+    sys_munmap(addr, len)
+}
+/// changes the protection of the pages in `[addr, addr + len)`, an empty `prot` forbids any access
+pub fn mprotect(addr: usize, len: usize, prot: MmapProt) -> isize {
+    sys_mprotect(addr, len, prot.bits())
+}
+/// attaches `len` bytes of the shared memory named `key` (0 for a new one) at `addr`, `munmap` detaches it
+pub fn shmat(key: usize, len: usize, addr: usize) -> isize {
+    sys_shmat(key, len, addr)
+}
+/// moves the program break by `increment` bytes, returns the old break
+pub fn sbrk(increment: isize) -> isize {
+    sys_sbrk(increment)
+}
+/// sets the program break to `addr`, returns 0 on success
+pub fn brk(addr: usize) -> isize {
+    let old = sbrk(0);
+    let ret = sbrk(addr as isize - old);
+    if ret < 0 {
+        ret
+    } else {
+        0
     }
 }
+///`mem_stat` 返回的内存使用情况，与内核中的布局一致，单位均为页
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct MemStat {
+    ///物理页帧总数
+    pub total_frames: usize,
+    ///空闲物理页帧数
+    pub free_frames: usize,
+    ///内核堆扩展时占用的物理页帧数，这些页帧不会被释放
+    pub heap_frames: usize,
+    ///内核堆已分配的字节数
+    pub heap_allocated_bytes: usize,
+    ///交换设备已使用的槽位数
+    pub swap_used_slots: usize,
+    ///开机以来从交换设备换入的页数
+    pub swap_page_ins: usize,
+    ///开机以来换出到交换设备的页数
+    pub swap_page_outs: usize,
+    ///进程驻留在内存中的用户页数，包括共享的页帧
+    pub resident_pages: usize,
+    ///进程被换出的用户页数
+    pub swapped_pages: usize,
+    ///进程页表占用的物理页帧数
+    pub page_table_pages: usize,
+    ///进程所有线程的用户栈驻留在内存中的页数之和
+    pub stack_pages: usize,
+}
+
+/// fills `stat` with the memory usage of the calling process and the whole system
+pub fn mem_stat(stat: &mut MemStat) -> isize {
+    sys_mem_stat(stat)
+}
+/// `waitpid` returns 0 at once instead of blocking if no child has exited
+pub const WNOHANG: usize = 1;
+
+pub fn wait(exit_code: &mut i32) -> isize {
+    // -ECHILD or a real pid
+    sys_waitpid(-1, exit_code as *mut _, 0)
+}
+
+pub fn waitpid(pid: usize, exit_code: &mut i32) -> isize {
+    // -ECHILD or a real pid
+    sys_waitpid(pid as isize, exit_code as *mut _, 0)
+}
+
+/// pid -1 means any child; returns 0 if no matching child has exited yet
+pub fn waitpid_nohang(pid: isize, exit_code: &mut i32) -> isize {
+    sys_waitpid(pid, exit_code as *mut _, WNOHANG)
+}
 
 pub fn sleep(sleep_ms: usize) {
     sys_sleep(sleep_ms);
@@ -165,15 +408,20 @@ pub fn gettid() -> isize {
     sys_gettid()
 }
 
+/// blocks until the thread exits, returns its exit code
 pub fn waittid(tid: usize) -> isize {
-    loop {
-        match sys_waittid(tid) {
-            -2 => {
-                yield_();
-            }
-            exit_code => return exit_code,
-        }
-    }
+    sys_waittid(tid)
+}
+
+/// exits the calling thread only, the whole process exits if it is the main thread
+pub fn thread_exit(exit_code: i32) -> ! {
+    sys_thread_exit(exit_code);
+}
+
+/// resources of a detached thread are reclaimed as soon as it exits,
+/// it can no longer be waited for
+pub fn thread_detach(tid: usize) -> isize {
+    sys_thread_detach(tid)
 }
 
 pub fn monitor_create() -> usize {
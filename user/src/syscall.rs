@@ -1,17 +1,39 @@
+use super::{MemStat, PeriodicStat, Stat};
 use core::arch::asm;
 
+const SYSCALL_DUP: usize = 23;
+const SYSCALL_DUP2: usize = 24;
+const SYSCALL_UNLINK: usize = 35;
+const SYSCALL_OPEN: usize = 56;
+const SYSCALL_CLOSE: usize = 57;
+const SYSCALL_PIPE: usize = 59;
+const SYSCALL_LSEEK: usize = 62;
 const SYSCALL_READ: usize = 63;
 const SYSCALL_WRITE: usize = 64;
+const SYSCALL_FSTAT: usize = 80;
 const SYSCALL_EXIT: usize = 93;
+const SYSCALL_SCHED_SETSCHEDULER: usize = 119;
 const SYSCALL_YIELD: usize = 124;
+const SYSCALL_SET_PRIORITY: usize = 140;
 const SYSCALL_GET_TIME: usize = 169;
 const SYSCALL_GETPID: usize = 172;
+const SYSCALL_SHMAT: usize = 196;
+const SYSCALL_SBRK: usize = 214;
+const SYSCALL_MUNMAP: usize = 215;
 const SYSCALL_FORK: usize = 220;
 const SYSCALL_EXEC: usize = 221;
+const SYSCALL_MMAP: usize = 222;
+const SYSCALL_MPROTECT: usize = 226;
 const SYSCALL_WAITPID: usize = 260;
 const SYSCALL_THREAD_CREATE: usize = 1000;
 const SYSCALL_GETTID: usize = 1001;
 const SYSCALL_WAITTID: usize = 1002;
+const SYSCALL_THREAD_EXIT: usize = 1003;
+const SYSCALL_THREAD_DETACH: usize = 1004;
+const SYSCALL_MEM_STAT: usize = 2000;
+const SYSCALL_SET_PERIODIC: usize = 2001;
+const SYSCALL_WAIT_NEXT_PERIOD: usize = 2002;
+const SYSCALL_PERIODIC_STAT: usize = 2003;
 const SYSCALL_SLEEP: usize = 101;
 const SYSCALL_MUTEX_CREATE: usize = 501;
 const SYSCALL_MUTEX_LOCK: usize = 502;
@@ -44,6 +66,34 @@ fn syscall(id: usize, args: [usize; 3]) -> isize {
     ret
 }
 
+pub fn sys_dup(fd: usize) -> isize {
+    syscall(SYSCALL_DUP, [fd, 0, 0])
+}
+
+pub fn sys_dup2(old_fd: usize, new_fd: usize) -> isize {
+    syscall(SYSCALL_DUP2, [old_fd, new_fd, 0])
+}
+
+pub fn sys_unlink(path: &str) -> isize {
+    syscall(SYSCALL_UNLINK, [path.as_ptr() as usize, 0, 0])
+}
+
+pub fn sys_open(path: &str, flags: u32) -> isize {
+    syscall(SYSCALL_OPEN, [path.as_ptr() as usize, flags as usize, 0])
+}
+
+pub fn sys_close(fd: usize) -> isize {
+    syscall(SYSCALL_CLOSE, [fd, 0, 0])
+}
+
+pub fn sys_pipe(pipe: &mut [usize; 2]) -> isize {
+    syscall(SYSCALL_PIPE, [pipe.as_mut_ptr() as usize, 0, 0])
+}
+
+pub fn sys_lseek(fd: usize, offset: isize, whence: usize) -> isize {
+    syscall(SYSCALL_LSEEK, [fd, offset as usize, whence])
+}
+
 pub fn sys_read(fd: usize, buffer: &mut [u8]) -> isize {
     syscall(
         SYSCALL_READ,
@@ -55,6 +105,10 @@ pub fn sys_write(fd: usize, buffer: &[u8]) -> isize {
     syscall(SYSCALL_WRITE, [fd, buffer.as_ptr() as usize, buffer.len()])
 }
 
+pub fn sys_fstat(fd: usize, st: &mut Stat) -> isize {
+    syscall(SYSCALL_FSTAT, [fd, st as *mut _ as usize, 0])
+}
+
 pub fn sys_exit(exit_code: i32) -> ! {
     syscall(SYSCALL_EXIT, [exit_code as usize, 0, 0]);
     panic!("sys_exit never returns!");
@@ -64,6 +118,26 @@ pub fn sys_yield() -> isize {
     syscall(SYSCALL_YIELD, [0, 0, 0])
 }
 
+pub fn sys_sched_setscheduler(policy: usize, priority: usize) -> isize {
+    syscall(SYSCALL_SCHED_SETSCHEDULER, [policy, priority, 0])
+}
+
+pub fn sys_set_periodic(period: usize, budget: usize, deadline: usize) -> isize {
+    syscall(SYSCALL_SET_PERIODIC, [period, budget, deadline])
+}
+
+pub fn sys_wait_next_period() -> isize {
+    syscall(SYSCALL_WAIT_NEXT_PERIOD, [0, 0, 0])
+}
+
+pub fn sys_periodic_stat(stat: &mut PeriodicStat) -> isize {
+    syscall(SYSCALL_PERIODIC_STAT, [stat as *mut _ as usize, 0, 0])
+}
+
+pub fn sys_set_priority(prio: isize) -> isize {
+    syscall(SYSCALL_SET_PRIORITY, [prio as usize, 0, 0])
+}
+
 pub fn sys_get_time() -> isize {
     syscall(SYSCALL_GET_TIME, [0, 0, 0])
 }
@@ -80,8 +154,32 @@ pub fn sys_exec(path: &str) -> isize {
     syscall(SYSCALL_EXEC, [path.as_ptr() as usize, 0, 0])
 }
 
-pub fn sys_waitpid(pid: isize, exit_code: *mut i32) -> isize {
-    syscall(SYSCALL_WAITPID, [pid as usize, exit_code as usize, 0])
+pub fn sys_sbrk(increment: isize) -> isize {
+    syscall(SYSCALL_SBRK, [increment as usize, 0, 0])
+}
+
+pub fn sys_mmap(addr: usize, len: usize, prot: usize) -> isize {
+    syscall(SYSCALL_MMAP, [addr, len, prot])
+}
+
+pub fn sys_munmap(addr: usize, len: usize) -> isize {
+    syscall(SYSCALL_MUNMAP, [addr, len, 0])
+}
+
+pub fn sys_shmat(key: usize, len: usize, addr: usize) -> isize {
+    syscall(SYSCALL_SHMAT, [key, len, addr])
+}
+
+pub fn sys_mprotect(addr: usize, len: usize, prot: usize) -> isize {
+    syscall(SYSCALL_MPROTECT, [addr, len, prot])
+}
+
+pub fn sys_mem_stat(stat: &mut MemStat) -> isize {
+    syscall(SYSCALL_MEM_STAT, [stat as *mut _ as usize, 0, 0])
+}
+
+pub fn sys_waitpid(pid: isize, exit_code: *mut i32, options: usize) -> isize {
+    syscall(SYSCALL_WAITPID, [pid as usize, exit_code as usize, options])
 }
 
 pub fn sys_thread_create(entry: usize, arg: usize) -> isize {
@@ -96,6 +194,15 @@ pub fn sys_waittid(tid: usize) -> isize {
     syscall(SYSCALL_WAITTID, [tid, 0, 0])
 }
 
+pub fn sys_thread_exit(exit_code: i32) -> ! {
+    syscall(SYSCALL_THREAD_EXIT, [exit_code as usize, 0, 0]);
+    panic!("sys_thread_exit never returns!");
+}
+
+pub fn sys_thread_detach(tid: usize) -> isize {
+    syscall(SYSCALL_THREAD_DETACH, [tid, 0, 0])
+}
+
 pub fn sys_sleep(ms: usize) -> isize {
     syscall(SYSCALL_SLEEP, [ms, 0, 0])
 }